@@ -26,62 +26,579 @@ impl std::error::Error for Error {
     }
 }
 
-// Do the sprite display on the CRT according to the rules
-fn crt_update(cycle: i64, regx: i64) -> i64 {
-    let hpos = cycle % 40;
-    if regx - 1 <= hpos && regx + 1 >= hpos {
-        print!("#");
-    } else {
-        print!(".");
+// the CPU registers, add a field here to add a register
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Registers {
+    x: i64,
+}
+
+impl Registers {
+    fn new() -> Self {
+        Self { x: 1 }
     }
 
-    if hpos == 39 {
-        println!();
+    fn get(&self, r: Register) -> i64 {
+        match r {
+            Register::X => self.x,
+        }
     }
-    cycle + 1
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let input = include_str!("../../signal_strength.dat");
-    let mut regx: i64 = 1;
-    let mut cycle: i64 = 0;
-    let mut sum_strength: i64 = 0;
+// register names for use in debugger conditions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Register {
+    X,
+}
+
+impl Register {
+    fn parse(name: &str) -> Option<Self> {
+        match name {
+            "x" | "X" => Some(Register::X),
+            _ => None,
+        }
+    }
+}
+
+// a decoded instruction, add a variant here and to parse, cycles and execute
+// to extend the instruction set
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Instruction {
+    Noop,
+    Addx(i64),
+}
+
+impl Instruction {
+    fn parse(line: &str) -> Result<Self, Error> {
+        let c: Vec<&str> = line.split_whitespace().collect();
+        match c.as_slice() {
+            ["noop"] => Ok(Instruction::Noop),
+            ["addx", v] => match v.parse() {
+                Ok(v) => Ok(Instruction::Addx(v)),
+                Err(_) => Err(Error::new(&format!("Error: bad operand in '{}'", line))),
+            },
+            _ => Err(Error::new(&format!("Error: bad instruction '{}'", line))),
+        }
+    }
+
+    // number of cycles the instruction takes to complete
+    fn cycles(&self) -> usize {
+        match self {
+            Instruction::Noop => 1,
+            Instruction::Addx(_) => 2,
+        }
+    }
 
-    let coms: Vec<&str> = input.split('\n').collect();
+    // the effect of the instruction, applied at the end of its last cycle
+    fn execute(&self, regs: &mut Registers) {
+        match self {
+            Instruction::Noop => {}
+            Instruction::Addx(v) => regs.x += v,
+        }
+    }
+}
 
-    for com in coms {
-        if com.is_empty() {
+impl std::fmt::Display for Instruction {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Instruction::Noop => write!(f, "noop"),
+            Instruction::Addx(v) => write!(f, "addx {}", v),
+        }
+    }
+}
+
+// decode a program, one instruction per line
+fn parse_program(input: &str) -> Result<Vec<Instruction>, Error> {
+    let mut program = vec![];
+    for (i, l) in input.lines().enumerate() {
+        if l.is_empty() {
             continue;
         }
+        match Instruction::parse(l) {
+            Ok(ins) => program.push(ins),
+            Err(e) => return Err(Error::new(&format!("line {}: {}", i + 1, e))),
+        }
+    }
+    Ok(program)
+}
+
+// the state of the CPU during a cycle, cycles are numbered from 1
+#[derive(Debug, Clone, Copy)]
+struct Tick {
+    cycle: usize,
+    pc: usize, // index of the instruction executing during the cycle
+    regs: Registers,
+}
+
+// a cycle accurate CPU, iterating over it runs the program one cycle at a time
+// yielding the register values during each cycle
+struct Cpu {
+    program: Vec<Instruction>,
+    regs: Registers,
+    pc: usize,        // index of the current instruction
+    cycle: usize,     // number of cycles completed
+    remaining: usize, // cycles left for the current instruction, 0 if none started
+}
+
+impl Cpu {
+    fn new(program: Vec<Instruction>) -> Self {
+        Self {
+            program,
+            regs: Registers::new(),
+            pc: 0,
+            cycle: 0,
+            remaining: 0,
+        }
+    }
+}
+
+impl Iterator for Cpu {
+    type Item = Tick;
+
+    fn next(&mut self) -> Option<Tick> {
+        if self.remaining == 0 {
+            self.remaining = self.program.get(self.pc)?.cycles();
+        }
 
-        let c: Vec<&str> = com.split(' ').collect();
-        let instruction = match c[0] {
-            "noop" => c[0],
-            "addx" => c[0],
-            _ => return Err(Box::new(Error::new("Error: bad instruction"))),
+        self.cycle += 1;
+        let tick = Tick {
+            cycle: self.cycle,
+            pc: self.pc,
+            regs: self.regs,
         };
 
-        if instruction == "addx" {
-            cycle = crt_update(cycle, regx);
-            if cycle <= 220 && cycle % 40 == 20 {
-                sum_strength += cycle * regx;
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            self.program[self.pc].execute(&mut self.regs);
+            self.pc += 1;
+        }
+        Some(tick)
+    }
+}
+
+// something that watches the CPU every cycle
+trait Observer {
+    fn observe(&mut self, tick: &Tick);
+}
+
+// sums the signal strength, cycle times X, at cycles first, first + period, ... up to last
+struct SignalSampler {
+    first: usize,
+    period: usize,
+    last: usize,
+    sum: i64,
+}
+
+impl SignalSampler {
+    fn new(first: usize, period: usize, last: usize) -> Self {
+        Self {
+            first,
+            period,
+            last,
+            sum: 0,
+        }
+    }
+}
+
+impl Observer for SignalSampler {
+    fn observe(&mut self, tick: &Tick) {
+        if tick.cycle >= self.first
+            && tick.cycle <= self.last
+            && (tick.cycle - self.first).is_multiple_of(self.period)
+        {
+            self.sum += tick.cycle as i64 * tick.regs.x;
+        }
+    }
+}
+
+const CRT_WIDTH: usize = 40;
+const CRT_HEIGHT: usize = 6;
+
+// the standard 4x6 block letters, each letter is 5 pixels wide including a blank column
+const FONT: [(char, [&str; 6]); 18] = [
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"]),
+    (' ', ["....", "....", "....", "....", "....", "...."]),
+];
+
+// the pixels of the CRT screen
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Framebuffer {
+    pixels: [[bool; CRT_WIDTH]; CRT_HEIGHT],
+}
+
+impl Framebuffer {
+    fn new() -> Self {
+        Self {
+            pixels: [[false; CRT_WIDTH]; CRT_HEIGHT],
+        }
+    }
+
+    // the screen as rows of '#' and '.'
+    fn render(&self) -> String {
+        let mut out = String::new();
+        for row in &self.pixels {
+            out.extend(row.iter().map(|&p| if p { '#' } else { '.' }));
+            out.push('\n');
+        }
+        out
+    }
+
+    // read the letters on the screen, '?' for any that isn't in the font
+    fn ocr(&self) -> String {
+        let mut text = String::new();
+        for cell in 0..CRT_WIDTH / 5 {
+            let glyph = FONT.iter().find(|(_, rows)| {
+                rows.iter().enumerate().all(|(y, r)| {
+                    r.chars()
+                        .enumerate()
+                        .all(|(x, c)| (c == '#') == self.pixels[y][cell * 5 + x])
+                })
+            });
+            text.push(glyph.map_or('?', |g| g.0));
+        }
+        text.trim().to_string()
+    }
+}
+
+// the CRT, drawing a pixel each cycle where the 3 pixel wide sprite is centred on X
+// it wraps back to the top left after the last pixel
+struct Crt {
+    fb: Framebuffer,
+}
+
+impl Crt {
+    fn new() -> Self {
+        Self {
+            fb: Framebuffer::new(),
+        }
+    }
+}
+
+// the (row, column) of the pixel drawn during a cycle and whether it is lit
+fn crt_pixel(tick: &Tick) -> (usize, usize, bool) {
+    let pos = (tick.cycle - 1) % (CRT_WIDTH * CRT_HEIGHT);
+    let hpos = pos % CRT_WIDTH;
+    (
+        pos / CRT_WIDTH,
+        hpos,
+        (tick.regs.x - hpos as i64).abs() <= 1,
+    )
+}
+
+impl Observer for Crt {
+    fn observe(&mut self, tick: &Tick) {
+        let (row, col, lit) = crt_pixel(tick);
+        self.fb.pixels[row][col] = lit;
+    }
+}
+
+// one line of a per-cycle trace: X, the sprite position and the pixel drawn
+fn trace_line(tick: &Tick) -> String {
+    let (row, col, lit) = crt_pixel(tick);
+    format!(
+        "{:>5} {:>5} {:>5}..{:<5} ({}, {:>2}) {}",
+        tick.cycle,
+        tick.regs.x,
+        tick.regs.x - 1,
+        tick.regs.x + 1,
+        row,
+        col,
+        if lit { '#' } else { '.' }
+    )
+}
+
+// prints a trace line for every cycle
+struct Tracer;
+
+impl Observer for Tracer {
+    fn observe(&mut self, tick: &Tick) {
+        println!("{}", trace_line(tick));
+    }
+}
+
+// the program listing with the cycles each instruction executes in
+fn disassemble(program: &[Instruction]) -> String {
+    let mut out = String::new();
+    let mut cycle = 1;
+    for (pc, ins) in program.iter().enumerate() {
+        let end = cycle + ins.cycles() - 1;
+        out += &format!("{:>5}  {:>5}-{:<5}  {}\n", pc, cycle, end, ins);
+        cycle = end + 1;
+    }
+    out
+}
+
+// run the CPU to the end of its program, showing every cycle to each observer
+fn run(cpu: Cpu, observers: &mut [&mut dyn Observer]) {
+    for tick in cpu {
+        for o in observers.iter_mut() {
+            o.observe(&tick);
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Cmp {
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+}
+
+// a condition that stops the debugger when it holds during a cycle
+#[derive(Debug, Clone, Copy)]
+enum Breakpoint {
+    Cycle(usize),
+    Reg(Register, Cmp, i64),
+}
+
+impl Breakpoint {
+    // parse "cycle 20" or a register condition such as "x >= 5"
+    fn parse(words: &[&str]) -> Result<Self, String> {
+        match words {
+            ["cycle", n] => n
+                .parse()
+                .map(Breakpoint::Cycle)
+                .map_err(|_| format!("bad cycle number {}", n)),
+            [reg, op, v] => {
+                let r = Register::parse(reg).ok_or(format!("unknown register {}", reg))?;
+                let cmp = match *op {
+                    "==" => Cmp::Eq,
+                    "!=" => Cmp::Ne,
+                    "<" => Cmp::Lt,
+                    "<=" => Cmp::Le,
+                    ">" => Cmp::Gt,
+                    ">=" => Cmp::Ge,
+                    _ => return Err(format!("unknown comparison {}", op)),
+                };
+                let v = v.parse().map_err(|_| format!("bad value {}", v))?;
+                Ok(Breakpoint::Reg(r, cmp, v))
+            }
+            _ => Err("use 'break cycle <n>' or 'break <reg> <op> <value>'".to_string()),
+        }
+    }
+
+    fn hit(&self, tick: &Tick) -> bool {
+        match *self {
+            Breakpoint::Cycle(c) => tick.cycle == c,
+            Breakpoint::Reg(r, cmp, v) => {
+                let val = tick.regs.get(r);
+                match cmp {
+                    Cmp::Eq => val == v,
+                    Cmp::Ne => val != v,
+                    Cmp::Lt => val < v,
+                    Cmp::Le => val <= v,
+                    Cmp::Gt => val > v,
+                    Cmp::Ge => val >= v,
+                }
+            }
+        }
+    }
+}
+
+// runs the CPU and CRT a cycle at a time, stopping at breakpoints
+struct Debugger {
+    cpu: Cpu,
+    crt: Crt,
+    breakpoints: Vec<Breakpoint>,
+}
+
+impl Debugger {
+    fn new(cpu: Cpu) -> Self {
+        Self {
+            cpu,
+            crt: Crt::new(),
+            breakpoints: vec![],
+        }
+    }
+
+    fn step(&mut self) -> Option<Tick> {
+        let tick = self.cpu.next()?;
+        self.crt.observe(&tick);
+        Some(tick)
+    }
+
+    // run until a breakpoint is hit, returning the cycle and the breakpoint's index,
+    // or the last cycle with None if the program ends first
+    fn cont(&mut self) -> Option<(Tick, Option<usize>)> {
+        let mut last = None;
+        while let Some(tick) = self.step() {
+            if let Some(b) = self.breakpoints.iter().position(|b| b.hit(&tick)) {
+                return Some((tick, Some(b)));
+            }
+            last = Some(tick);
+        }
+        last.map(|t| (t, None))
+    }
+
+    // describe where a cycle is in the program
+    fn describe(&self, tick: &Tick) -> String {
+        format!(
+            "{}  [{}: {}]",
+            trace_line(tick),
+            tick.pc,
+            self.cpu.program[tick.pc]
+        )
+    }
+
+    // carry out one debugger command, returns false to quit
+    fn command(&mut self, line: &str) -> bool {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            [] => {}
+            ["s" | "step", rest @ ..] => {
+                let n = rest.first().and_then(|n| n.parse().ok()).unwrap_or(1);
+                for _ in 0..n {
+                    match self.step() {
+                        Some(t) => println!("{}", self.describe(&t)),
+                        None => {
+                            println!("program finished");
+                            break;
+                        }
+                    }
+                }
             }
-            cycle = crt_update(cycle, regx);
-            if cycle <= 220 && cycle % 40 == 20 {
-                sum_strength += cycle * regx;
+            ["c" | "continue"] => match self.cont() {
+                Some((t, Some(b))) => {
+                    println!("breakpoint {}: {:?}", b + 1, self.breakpoints[b]);
+                    println!("{}", self.describe(&t));
+                }
+                Some((t, None)) => println!("program finished after cycle {}", t.cycle),
+                None => println!("program finished"),
+            },
+            ["b" | "break", cond @ ..] => match Breakpoint::parse(cond) {
+                Ok(b) => {
+                    self.breakpoints.push(b);
+                    println!("breakpoint {}: {:?}", self.breakpoints.len(), b);
+                }
+                Err(e) => println!("{}", e),
+            },
+            ["d" | "delete", n] => match n.parse::<usize>() {
+                Ok(n) if n >= 1 && n <= self.breakpoints.len() => {
+                    self.breakpoints.remove(n - 1);
+                }
+                _ => println!("no breakpoint {}", n),
+            },
+            ["l" | "list"] => {
+                for (i, b) in self.breakpoints.iter().enumerate() {
+                    println!("breakpoint {}: {:?}", i + 1, b);
+                }
             }
+            ["p" | "print"] => println!(
+                "after cycle {}: pc {}, {:?}",
+                self.cpu.cycle, self.cpu.pc, self.cpu.regs
+            ),
+            ["screen"] => print!("{}", self.crt.fb.render()),
+            ["disasm"] => print!("{}", disassemble(&self.cpu.program)),
+            ["q" | "quit"] => return false,
+            _ => println!(
+                "commands: step [n], continue, break cycle <n>, break <reg> <op> <value>, \
+                 delete <n>, list, print, screen, disasm, quit"
+            ),
+        }
+        true
+    }
+}
+
+// read debugger commands from stdin until quit or end of input
+fn debug(cpu: Cpu) -> std::io::Result<()> {
+    use std::io::{BufRead, Write};
 
-            let addend: i64 = c[1].parse()?;
-            regx += addend;
-        } else {
-            cycle = crt_update(cycle, regx);
-            if cycle <= 220 && cycle % 40 == 20 {
-                sum_strength += cycle * regx;
+    let mut dbg = Debugger::new(cpu);
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("(dbg) ");
+        std::io::stdout().flush()?;
+        match lines.next() {
+            Some(line) => {
+                if !dbg.command(&line?) {
+                    break;
+                }
             }
+            None => {
+                println!();
+                break;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let input = include_str!("../../signal_strength.dat");
+    let cpu = Cpu::new(parse_program(input)?);
+
+    // "debug" starts the interactive debugger, "trace" prints every cycle and
+    // "disasm" lists the program with its cycle numbers
+    match std::env::args().nth(1).as_deref() {
+        Some("debug") => {
+            debug(cpu)?;
+            return Ok(());
+        }
+        Some("trace") => {
+            println!("cycle     X  sprite       pixel");
+            run(cpu, &mut [&mut Tracer]);
+            return Ok(());
+        }
+        Some("disasm") => {
+            print!("{}", disassemble(&cpu.program));
+            return Ok(());
         }
+        _ => {}
     }
 
-    println!("aoc10a: {}", sum_strength);
+    let mut sampler = SignalSampler::new(20, 40, 220);
+    let mut crt = Crt::new();
+    run(cpu, &mut [&mut sampler, &mut crt]);
+
+    print!("{}", crt.fb.render());
+    println!("aoc10a: {}", sampler.sum);
+    println!("aoc10b: {}", crt.fb.ocr());
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ocr_reads_puzzle_screen() {
+        let cpu = Cpu::new(parse_program(include_str!("../../signal_strength.dat")).unwrap());
+        let mut crt = Crt::new();
+        run(cpu, &mut [&mut crt]);
+        assert_eq!(crt.fb.ocr(), "EHZFZHCZ");
+    }
+
+    #[test]
+    fn ocr_marks_unknown_glyphs() {
+        let mut fb = Framebuffer::new();
+        let (_, h) = FONT.iter().find(|(c, _)| *c == 'H').unwrap();
+        for (y, row) in h.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                fb.pixels[y][x] = c == '#';
+            }
+        }
+        // a lone pixel isn't any letter
+        fb.pixels[2][6] = true;
+        assert_eq!(fb.ocr(), "H?");
+    }
+}
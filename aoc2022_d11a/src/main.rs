@@ -1,29 +1,219 @@
 // AoC 2022 day 11
 
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Op {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+// a worry level, signed so that "-" means the same with or without the modulus:
+// old - 100 can go below zero either way, reducing modulo m keeps the residue of the
+// negative level, and divisibility tests hold for negative levels too
+// division, including relief, rounds toward zero
+type Worry = i128;
+
+// the right hand side of a monkey's "new = ..." operation
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Expr {
+    Old,
+    Num(usize),
+    Bin(Box<Expr>, Op, Box<Expr>),
+}
+
+impl Expr {
+    // parse an expression of old, numbers, + - * / and parentheses
+    // with * and / binding tighter than + and -
+    fn parse(s: &str) -> Result<Expr, String> {
+        let tokens = tokenize(s)?;
+        let mut pos = 0;
+        let e = parse_sum(&tokens, &mut pos)?;
+        if pos != tokens.len() {
+            return Err(format!("unexpected {:?} in '{}'", tokens[pos], s.trim()));
+        }
+        Ok(e)
+    }
+
+    // evaluate exactly, failing rather than wrapping if the worry level overflows
+    fn eval(&self, old: Worry) -> Result<Worry, String> {
+        match self {
+            Expr::Old => Ok(old),
+            Expr::Num(n) => Ok(*n as Worry),
+            Expr::Bin(l, op, r) => {
+                let (a, b) = (l.eval(old)?, r.eval(old)?);
+                match op {
+                    Op::Add => a
+                        .checked_add(b)
+                        .ok_or(format!("worry level {} + {} overflows", a, b)),
+                    Op::Sub => a
+                        .checked_sub(b)
+                        .ok_or(format!("worry level {} - {} overflows", a, b)),
+                    Op::Mul => a
+                        .checked_mul(b)
+                        .ok_or(format!("worry level {} * {} overflows", a, b)),
+                    Op::Div => a
+                        .checked_div(b)
+                        .ok_or(format!("worry level {} divided by zero", a)),
+                }
+            }
+        }
+    }
+
+    // true if the expression uses '/' anywhere
+    fn divides(&self) -> bool {
+        match self {
+            Expr::Bin(l, op, r) => *op == Op::Div || l.divides() || r.divides(),
+            _ => false,
+        }
+    }
+
+    // evaluate modulo m, giving the residue in 0..m, which only preserves divisibility
+    // by factors of m for + - and *, so division is refused
+    // residues are worked on as u128 so products can't overflow for any usize m
+    fn eval_mod(&self, old: Worry, m: usize) -> Result<Worry, String> {
+        match self {
+            Expr::Old => Ok(old.rem_euclid(m as Worry)),
+            Expr::Num(n) => Ok((n % m) as Worry),
+            Expr::Bin(l, op, r) => {
+                let (a, b) = (l.eval_mod(old, m)? as u128, r.eval_mod(old, m)? as u128);
+                let m = m as u128;
+                match op {
+                    Op::Add => Ok(((a + b) % m) as Worry),
+                    Op::Sub => Ok(((a + m - b) % m) as Worry),
+                    Op::Mul => Ok((a * b % m) as Worry),
+                    Op::Div => {
+                        Err("an operation using '/' can't be reduced by a modulus".to_string())
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Old,
+    Num(usize),
+    Op(Op),
+    Open,
+    Close,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = vec![];
+    let mut chars = s.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        match c {
+            ' ' => {
+                chars.next();
+            }
+            '+' | '-' | '*' | '/' => {
+                chars.next();
+                tokens.push(Token::Op(match c {
+                    '+' => Op::Add,
+                    '-' => Op::Sub,
+                    '*' => Op::Mul,
+                    _ => Op::Div,
+                }));
+            }
+            '(' | ')' => {
+                chars.next();
+                tokens.push(if c == '(' { Token::Open } else { Token::Close });
+            }
+            '0'..='9' => {
+                let mut lit = String::new();
+                let mut n = Some(0_usize);
+                while let Some(d) = chars.peek().and_then(|c| c.to_digit(10)) {
+                    lit.push(chars.next().unwrap());
+                    n = n
+                        .and_then(|n| n.checked_mul(10))
+                        .and_then(|n| n.checked_add(d as usize));
+                }
+                let n = n.ok_or(format!("number {} in '{}' is too large", lit, s.trim()))?;
+                tokens.push(Token::Num(n));
+            }
+            _ => {
+                let mut word = String::new();
+                while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric()) {
+                    word.push(c);
+                    chars.next();
+                }
+                if word.is_empty() {
+                    return Err(format!("unexpected '{}' in '{}'", c, s.trim()));
+                } else if word != "old" {
+                    return Err(format!("unknown term '{}' in '{}'", word, s.trim()));
+                }
+                tokens.push(Token::Old);
+            }
+        }
+    }
+    Ok(tokens)
+}
+
+// sum := product (('+' | '-') product)*
+fn parse_sum(t: &[Token], pos: &mut usize) -> Result<Expr, String> {
+    let mut e = parse_product(t, pos)?;
+    while let Some(Token::Op(op @ (Op::Add | Op::Sub))) = t.get(*pos) {
+        *pos += 1;
+        e = Expr::Bin(Box::new(e), *op, Box::new(parse_product(t, pos)?));
+    }
+    Ok(e)
+}
+
+// product := term (('*' | '/') term)*
+fn parse_product(t: &[Token], pos: &mut usize) -> Result<Expr, String> {
+    let mut e = parse_term(t, pos)?;
+    while let Some(Token::Op(op @ (Op::Mul | Op::Div))) = t.get(*pos) {
+        *pos += 1;
+        e = Expr::Bin(Box::new(e), *op, Box::new(parse_term(t, pos)?));
+    }
+    Ok(e)
+}
+
+// term := 'old' | number | '(' sum ')'
+fn parse_term(t: &[Token], pos: &mut usize) -> Result<Expr, String> {
+    *pos += 1;
+    match t.get(*pos - 1) {
+        Some(Token::Old) => Ok(Expr::Old),
+        Some(Token::Num(n)) => Ok(Expr::Num(*n)),
+        Some(Token::Open) => {
+            let e = parse_sum(t, pos)?;
+            if t.get(*pos) != Some(&Token::Close) {
+                return Err("missing ')'".to_string());
+            }
+            *pos += 1;
+            Ok(e)
+        }
+        Some(tok) => Err(format!("unexpected {:?}", tok)),
+        None => Err("expression ends early".to_string()),
+    }
+}
+
+// an item and its current worry level, ids number the items in input order
+#[derive(Debug, Clone, Copy)]
+struct Item {
+    id: usize,
+    worry: Worry,
+}
+
 #[derive(Debug, Clone)]
 // location or move step
 struct Monkey {
-    items: Vec<usize>,
-    operator: char,
-    operand: usize,
+    items: Vec<Item>,
+    op: Expr,
     testdiv: usize,
     trueidx: usize,
     falseidx: usize,
 }
 
 impl Monkey {
-    fn new(
-        items: Vec<usize>,
-        operator: char,
-        operand: usize,
-        testdiv: usize,
-        trueidx: usize,
-        falseidx: usize,
-    ) -> Self {
+    fn new(items: Vec<Item>, op: Expr, testdiv: usize, trueidx: usize, falseidx: usize) -> Self {
         Self {
             items,
-            operator,
-            operand,
+            op,
             testdiv,
             trueidx,
             falseidx,
@@ -31,54 +221,361 @@ impl Monkey {
     }
 }
 
+fn gcd(a: usize, b: usize) -> usize {
+    if b == 0 {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+// the least common multiple of all the test divisors, worry levels can be reduced
+// by it without changing the result of any monkey's test
+fn divisor_lcm(mv: &[Monkey]) -> Result<usize, String> {
+    let mut l: usize = 1;
+    for m in mv {
+        if m.testdiv == 0 {
+            return Err("a monkey tests for divisibility by 0".to_string());
+        }
+        l = (l / gcd(l, m.testdiv))
+            .checked_mul(m.testdiv)
+            .ok_or("the LCM of the test divisors is too large to represent")?;
+    }
+    Ok(l)
+}
+
+// the keep-away rules: worry is divided by relief after each inspection, with 1 meaning
+// no relief so worry is kept modulo the divisor LCM instead, or exactly if an operation
+// divides, and monkey business is
+// the product of the top_k inspection counts after the given number of rounds
+#[derive(Debug, Clone, Copy)]
+struct Rules {
+    relief: usize,
+    rounds: usize,
+    top_k: usize,
+}
+
+// one inspection of an item: the round, the monkey, the new worry level and
+// the monkey it was thrown to
+#[derive(Debug, Clone, Copy)]
+struct Hop {
+    round: usize,
+    monkey: usize,
+    worry: Worry,
+    to: usize,
+}
+
+// inspections made by each monkey during a round and items each holds at its end
+#[derive(Debug, Clone)]
+struct RoundStats {
+    inspections: Vec<usize>,
+    held: Vec<usize>,
+}
+
+// optional record of every item's journey and of each round
+#[derive(Debug, Default)]
+struct Tracker {
+    journeys: Vec<Vec<Hop>>, // indexed by item id
+    stats: Vec<RoundStats>,
+}
+
+impl Tracker {
+    fn new(num_items: usize) -> Self {
+        Self {
+            journeys: vec![vec![]; num_items],
+            stats: vec![],
+        }
+    }
+
+    // the per round statistics as CSV
+    fn stats_csv(&self) -> String {
+        let mut out = "round,monkey,inspections,items_held\n".to_string();
+        for (r, st) in self.stats.iter().enumerate() {
+            for (m, (ins, held)) in st.inspections.iter().zip(&st.held).enumerate() {
+                out += &format!("{},{},{},{}\n", r + 1, m, ins, held);
+            }
+        }
+        out
+    }
+}
+
 // perform a round of monkey business
-fn round(mv: &mut [Monkey], mh: &mut [usize], part2: bool, m: usize) -> Result<(), String> {
+fn round(
+    mv: &mut [Monkey],
+    mh: &mut [usize],
+    rules: &Rules,
+    m: Option<usize>,
+    rnd: usize,
+    mut tracker: Option<&mut Tracker>,
+) -> Result<(), String> {
+    let mut inspections = vec![0; mv.len()];
     for midx in 0..mv.len() {
         for i in 0..mv[midx].items.len() {
-            let worrylevel = match mv[midx].operator {
-                '+' => {
-                    if part2 {
-                        (mv[midx].items[i] + mv[midx].operand) % m
-                    } else {
-                        (mv[midx].items[i] + mv[midx].operand) / 3
-                    }
-                }
-                '*' => {
-                    if part2 {
-                        (mv[midx].items[i] * mv[midx].operand) % m
-                    } else {
-                        (mv[midx].items[i] * mv[midx].operand) / 3
-                    }
-                }
-                '^' => {
-                    if part2 {
-                        (mv[midx].items[i] * mv[midx].items[i]) % m
-                    } else {
-                        (mv[midx].items[i] * mv[midx].items[i]) / 3
-                    }
-                }
-                _ => return Err("bad operator".to_string()),
+            let mut item = mv[midx].items[i];
+            item.worry = match m {
+                Some(m) => mv[midx].op.eval_mod(item.worry, m)?,
+                None => mv[midx].op.eval(item.worry)? / rules.relief as Worry,
             };
-            if worrylevel % mv[midx].testdiv == 0 {
-                mv[mv[midx].trueidx].items.push(worrylevel);
+            let to = if item.worry % mv[midx].testdiv as Worry == 0 {
+                mv[midx].trueidx
             } else {
-                mv[mv[midx].falseidx].items.push(worrylevel);
-            }
+                mv[midx].falseidx
+            };
+            mv[to].items.push(item);
             mh[midx] += 1;
+            inspections[midx] += 1;
+
+            if let Some(t) = tracker.as_deref_mut() {
+                t.journeys[item.id].push(Hop {
+                    round: rnd,
+                    monkey: midx,
+                    worry: item.worry,
+                    to,
+                });
+            }
         }
         mv[midx].items.clear();
     }
 
+    if let Some(t) = tracker {
+        t.stats.push(RoundStats {
+            inspections,
+            held: mv.iter().map(|m| m.items.len()).collect(),
+        });
+    }
+    Ok(())
+}
+
+// run the rules on a copy of the monkeys, returning how many items each inspected
+fn simulate(
+    monkeys: &[Monkey],
+    rules: &Rules,
+    modulus: usize,
+    mut tracker: Option<&mut Tracker>,
+) -> Result<Vec<usize>, String> {
+    if rules.relief == 0 {
+        return Err("relief must be at least 1".to_string());
+    }
+    // the modulus can't preserve division, so with an operation using '/' worry levels
+    // are kept exactly and only an actual overflow is an error
+    let m = (rules.relief == 1 && !monkeys.iter().any(|mk| mk.op.divides())).then_some(modulus);
+    let mut mv = monkeys.to_vec();
+    let mut mh = vec![0; mv.len()];
+    for rnd in 1..=rules.rounds {
+        round(&mut mv, &mut mh, rules, m, rnd, tracker.as_deref_mut())?;
+    }
+    Ok(mh)
+}
+
+// the product of the top_k inspection counts
+fn monkey_business(handled: &[usize], top_k: usize) -> Result<u128, String> {
+    let mut mh = handled.to_vec();
+    mh.sort_unstable();
+    mh.reverse();
+    if top_k > mh.len() {
+        return Err(format!(
+            "can't take the top {} of {} monkeys",
+            top_k,
+            mh.len()
+        ));
+    }
+    mh.iter()
+        .take(top_k)
+        .try_fold(1_u128, |p, &n| p.checked_mul(n as u128))
+        .ok_or("monkey business overflows".to_string())
+}
+
+// the path of one item under the modular rules, which is periodic because there are
+// only so many (monkey, worry) states an item can be in at the start of a round
+#[derive(Debug)]
+struct ItemCycle {
+    id: usize,
+    start: usize,            // rounds before the item first enters its cycle
+    period: usize,           // rounds in the cycle
+    prefix: Vec<Vec<usize>>, // prefix[r] is the inspections by each monkey in the first r rounds
+}
+
+impl ItemCycle {
+    // follow the item round by round until it is back in a state it has been in before
+    fn find(monkeys: &[Monkey], item: Item, mut monkey: usize, m: usize) -> Result<Self, String> {
+        let mut seen: HashMap<(usize, Worry), usize> = HashMap::new();
+        let mut prefix = vec![vec![0; monkeys.len()]];
+        let mut worry = item.worry.rem_euclid(m as Worry);
+        loop {
+            if let Some(&start) = seen.get(&(monkey, worry)) {
+                return Ok(Self {
+                    id: item.id,
+                    start,
+                    period: prefix.len() - 1 - start,
+                    prefix,
+                });
+            }
+            seen.insert((monkey, worry), prefix.len() - 1);
+
+            // the item keeps being inspected this round while it is thrown to later monkeys
+            let mut counts = prefix[prefix.len() - 1].clone();
+            loop {
+                let mk = &monkeys[monkey];
+                worry = mk.op.eval_mod(worry, m)?;
+                counts[monkey] += 1;
+                let to = if worry % mk.testdiv as Worry == 0 {
+                    mk.trueidx
+                } else {
+                    mk.falseidx
+                };
+                let waits = to <= monkey;
+                monkey = to;
+                if waits {
+                    break;
+                }
+            }
+            prefix.push(counts);
+        }
+    }
+
+    // inspections by each monkey of this item over the given number of rounds
+    fn inspections(&self, rounds: usize) -> Result<Vec<usize>, String> {
+        if rounds < self.prefix.len() {
+            return Ok(self.prefix[rounds].clone());
+        }
+        let laps = (rounds - self.start) / self.period;
+        let rest = (rounds - self.start) % self.period;
+        let before = &self.prefix[self.start];
+        let lap = &self.prefix[self.start + self.period];
+        let partial = &self.prefix[self.start + rest];
+        (0..before.len())
+            .map(|i| {
+                (lap[i] - before[i])
+                    .checked_mul(laps)
+                    .and_then(|n| n.checked_add(partial[i]))
+                    .ok_or(format!("inspection count of monkey {} overflows", i))
+            })
+            .collect()
+    }
+}
+
+// the inspections by each monkey after any number of rounds without relief, found from
+// the cycle of every item rather than by playing the rounds
+fn cycle_inspections(
+    monkeys: &[Monkey],
+    modulus: usize,
+    rounds: usize,
+) -> Result<(Vec<usize>, Vec<ItemCycle>), String> {
+    let mut handled = vec![0_usize; monkeys.len()];
+    let mut cycles = vec![];
+    for (midx, mk) in monkeys.iter().enumerate() {
+        for &item in &mk.items {
+            let cyc = ItemCycle::find(monkeys, item, midx, modulus)?;
+            for (h, n) in handled.iter_mut().zip(cyc.inspections(rounds)?) {
+                *h = h
+                    .checked_add(n)
+                    .ok_or("inspection count overflows".to_string())?;
+            }
+            cycles.push(cyc);
+        }
+    }
+    Ok((handled, cycles))
+}
+
+// run the "cycles <rounds> [--top n]" command
+fn run_cycles(monkeys: &[Monkey], modulus: usize, args: &[String]) -> Result<(), String> {
+    let rounds: usize = args
+        .first()
+        .and_then(|r| r.parse().ok())
+        .ok_or("cycles needs a number of rounds")?;
+    let top_k = match args.get(1).map(|a| a.as_str()) {
+        Some("--top") => args
+            .get(2)
+            .and_then(|n| n.parse().ok())
+            .ok_or("--top needs a number")?,
+        Some(a) => return Err(format!("unknown option {}", a)),
+        None => 2,
+    };
+
+    let (handled, cycles) = cycle_inspections(monkeys, modulus, rounds)?;
+    for cyc in &cycles {
+        println!(
+            "item {}: cycle of {} rounds after {} rounds",
+            cyc.id, cyc.period, cyc.start
+        );
+    }
+    for (i, n) in handled.iter().enumerate() {
+        println!("monkey {} inspected {} items", i, n);
+    }
+    println!(
+        "monkey business after {} rounds: {}",
+        rounds,
+        monkey_business(&handled, top_k)?
+    );
+    Ok(())
+}
+
+// run the "simulate" command, its options are --relief, --rounds and --top with a
+// number, --trace to print every item's journey and --csv <file> to write the round
+// statistics, "-" for stdout
+fn run_simulation(monkeys: &[Monkey], modulus: usize, args: &[String]) -> Result<(), String> {
+    let mut rules = Rules {
+        relief: 3,
+        rounds: 20,
+        top_k: 2,
+    };
+    let mut trace = false;
+    let mut csv = None;
+    let mut it = args.iter();
+    while let Some(a) = it.next() {
+        let mut num = || -> Result<usize, String> {
+            it.next()
+                .and_then(|n| n.parse().ok())
+                .ok_or(format!("{} needs a number", a))
+        };
+        match a.as_str() {
+            "--relief" => rules.relief = num()?,
+            "--rounds" => rules.rounds = num()?,
+            "--top" => rules.top_k = num()?,
+            "--trace" => trace = true,
+            "--csv" => csv = Some(it.next().ok_or("--csv needs a file name")?),
+            _ => return Err(format!("unknown option {}", a)),
+        }
+    }
+
+    // only keep the history if something is going to be shown from it
+    let num_items = monkeys.iter().map(|m| m.items.len()).sum();
+    let mut tracker = (trace || csv.is_some()).then(|| Tracker::new(num_items));
+    let handled = simulate(monkeys, &rules, modulus, tracker.as_mut())?;
+
+    if let (true, Some(t)) = (trace, &tracker) {
+        for (id, hops) in t.journeys.iter().enumerate() {
+            println!("item {}:", id);
+            for h in hops {
+                println!(
+                    "    round {}: monkey {} -> {}, worry {}",
+                    h.round, h.monkey, h.to, h.worry
+                );
+            }
+        }
+    }
+    match (csv.map(|c| c.as_str()), &tracker) {
+        (Some("-"), Some(t)) => print!("{}", t.stats_csv()),
+        (Some(path), Some(t)) => std::fs::write(path, t.stats_csv()).map_err(|e| e.to_string())?,
+        _ => {}
+    }
+
+    for (i, n) in handled.iter().enumerate() {
+        println!("monkey {} inspected {} items", i, n);
+    }
+    println!(
+        "monkey business: {}",
+        monkey_business(&handled, rules.top_k)?
+    );
     Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let input = include_str!("../../monkey_business.dat");
     let mut monkeyvec: Vec<Monkey> = vec![];
-    let mut mhandled: Vec<usize> = vec![]; // number of time each monkey handles items
-    let mut mi: Vec<usize> = vec![];
-    let mut mope: char = 'a';
-    let mut mopa: usize = 0;
+    let mut mi: Vec<Item> = vec![];
+    let mut num_items = 0;
+    let mut mop = Expr::Old;
     let mut mtd: usize = 0;
     let mut mti: usize = 0;
     let mut mfi: usize;
@@ -103,19 +600,20 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         i[0].parse::<usize>().unwrap()
                     })
                     .collect();
-                mi = items;
+                mi = items
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, worry)| Item {
+                        id: num_items + i,
+                        worry: worry as Worry,
+                    })
+                    .collect();
+                num_items += mi.len();
             }
             "new" => {
-                // operator - '+' or '*'
-                mope = words[4].chars().next().unwrap();
-
-                // deal with squaring by using '^' as a symbol
-                if mope == '*' && words[5] == "old" {
-                    mope = '^';
-                    mopa = 0;
-                } else {
-                    mopa = words[5].parse::<usize>().unwrap();
-                }
+                // everything after the '=' is the expression
+                let (_, rhs) = l.split_once('=').ok_or("missing '=' in operation")?;
+                mop = Expr::parse(rhs)?;
             }
 
             // divisor
@@ -129,42 +627,50 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 mfi = words[5].parse::<usize>().unwrap();
 
                 // got all info create a Monkey on monkeyvec
-                monkeyvec.push(Monkey::new(mi.to_owned(), mope, mopa, mtd, mti, mfi));
-                mhandled.push(0);
+                monkeyvec.push(Monkey::new(mi.to_owned(), mop.clone(), mtd, mti, mfi));
             }
             _ => {}
         }
     }
 
-    // create a modulus for part 2 that is a multiple of all the test divisors
-    let mut modulus = 100;
-    for m in &monkeyvec {
-        modulus *= m.testdiv;
-    }
+    // the modulus for part 2
+    let modulus = divisor_lcm(&monkeyvec)?;
 
-    // clone the data structures for part 2
-    let mut mv2 = monkeyvec.clone();
-    let mut mh2 = mhandled.clone();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|s| s.as_str()) == Some("simulate") {
+        run_simulation(&monkeyvec, modulus, &args[1..])?;
+        return Ok(());
+    }
+    if args.first().map(|s| s.as_str()) == Some("cycles") {
+        run_cycles(&monkeyvec, modulus, &args[1..])?;
+        return Ok(());
+    }
 
     // part 1
-    for _ in 0..20 {
-        round(&mut monkeyvec, &mut mhandled, false, modulus)?;
-    }
-    mhandled.sort_unstable();
-    mhandled.reverse();
+    let part1 = Rules {
+        relief: 3,
+        rounds: 20,
+        top_k: 2,
+    };
+    let mhandled = simulate(&monkeyvec, &part1, modulus, None)?;
 
     // part 2
-    for _ in 0..10000 {
-        round(&mut mv2, &mut mh2, true, modulus)?;
+    let part2 = Rules {
+        relief: 1,
+        rounds: 10000,
+        top_k: 2,
+    };
+    let ansa = monkey_business(&mhandled, part1.top_k)?;
+    match simulate(&monkeyvec, &part2, modulus, None)
+        .and_then(|mh| monkey_business(&mh, part2.top_k))
+    {
+        Ok(ansb) => println!("aoc11a: {}, aoc11b: {}", ansa, ansb),
+        Err(e) => {
+            // part 1 still stands if part 2 can't be worked out
+            println!("aoc11a: {}", ansa);
+            return Err(e.into());
+        }
     }
-    mh2.sort_unstable();
-    mh2.reverse();
-
-    println!(
-        "aoc11a: {}, aoc11b: {}",
-        mhandled[0] * mhandled[1],
-        mh2[0] * mh2[1]
-    );
 
     Ok(())
 }
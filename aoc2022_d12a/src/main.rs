@@ -1,6 +1,6 @@
 // AoC 2022 day 12
 
-use std::cmp::max;
+use std::collections::VecDeque;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Ord, PartialOrd)]
 // location or move step
@@ -22,64 +22,35 @@ struct Loc {
     numsteps: usize,
 }
 
-// function that does a DFS looking for the cheapest path from start to end
-// it returns the number of steps
-fn dfs(
-    cur: &Point,
-    steps: usize,
-    best_so_far: Option<usize>,
-    end: &Point,
-    v: &mut Vec<Vec<Loc>>,
-) -> Option<usize> {
-    // closure to find minimum number of steps to get to the end square, up or over
-    let dist_to_end = |x: usize, y: usize, h: usize| {
-        max(
-            (b'z' - b'a') as usize - h,
-            if end.x >= x { end.x - x } else { x - end.x }
-                + if end.y >= y { end.y - y } else { y - end.y },
-        )
-    };
-
-    if steps >= v[cur.y][cur.x].numsteps {
-        return None; // already a better path through this point
-    } else {
-        v[cur.y][cur.x].numsteps = steps; // best path so far
-    }
-
-    if cur == end {
-        return Some(steps);
-    }
-
-    // trys is an array of up to four next locations sorted in order of distance from the end loc
-    let mut trys: Vec<(usize, Point)> = vec![];
-    for x in cur.x.saturating_sub(1)..=cur.x + 1 {
-        for y in cur.y.saturating_sub(1)..=cur.y + 1 {
-            // third line below enforces that we can only go up 1 at a time
-            if x < v[cur.y].len()
-                && y < v.len()
-                && (x == cur.x && y != cur.y || x != cur.x && y == cur.y)
-                && v[cur.y][cur.x].height + 1 >= v[y][x].height
+// breadth first search backward from the end, stepping only where the forward climb
+// would be allowed, i.e. down any amount or up at most 1 when seen from the end
+// it fills in numsteps with the fewest steps from each location to the end
+fn bfs_from_end(end: &Point, v: &mut [Vec<Loc>]) {
+    let mut queue: VecDeque<Point> = VecDeque::new();
+    v[end.y][end.x].numsteps = 0;
+    queue.push_back(*end);
+
+    while let Some(cur) = queue.pop_front() {
+        let steps = v[cur.y][cur.x].numsteps;
+        let height = v[cur.y][cur.x].height;
+        let nbrs = [
+            (cur.x.wrapping_sub(1), cur.y),
+            (cur.x + 1, cur.y),
+            (cur.x, cur.y.wrapping_sub(1)),
+            (cur.x, cur.y + 1),
+        ];
+        for (x, y) in nbrs {
+            // a neighbour can reach cur if cur is at most 1 higher
+            if y < v.len()
+                && x < v[y].len()
+                && v[y][x].numsteps == usize::MAX
+                && v[y][x].height + 1 >= height
             {
-                let best_possible = dist_to_end(x, y, v[y][x].height) + steps + 1;
-                // don't try directions that cannot beat best_so_far
-                if best_so_far.is_none() || best_possible < best_so_far.unwrap() {
-                    trys.push((best_possible, Point::new(x, y)));
-                }
+                v[y][x].numsteps = steps + 1;
+                queue.push_back(Point::new(x, y));
             }
         }
     }
-
-    trys.sort_unstable();
-    let mut best = best_so_far;
-    for t in &trys {
-        if let Some(s) = dfs(&t.1, steps + 1, best, end, v) {
-            if best.is_none() || best.is_some() && s < best.unwrap() {
-                best = Some(s);
-            }
-        }
-    }
-
-    best
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -130,24 +101,21 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         hills.push(l);
     }
 
+    // one search answers both parts
+    bfs_from_end(&end, &mut hills);
+
     // part 1
-    if let Some(ans) = dfs(&start, 0, None, &end, &mut hills) {
-        println!("aoc12a: {}", ans);
-    } else {
-        println!("no path found");
+    match hills[start.y][start.x].numsteps {
+        usize::MAX => println!("no path found"),
+        ans => println!("aoc12a: {}", ans),
     }
 
-    // part 2 - to drastically speed up this part, we don't reset hills!
-    let mut very_best = usize::MAX;
-    for a in &all_as {
-        if let Some(ans) = dfs(a, 0, None, &end, &mut hills) {
-            if ans < very_best {
-                very_best = ans;
-            }
-        }
+    // part 2 - the best of every 'a', including the start
+    all_as.push(start);
+    match all_as.iter().map(|a| hills[a.y][a.x].numsteps).min() {
+        None | Some(usize::MAX) => println!("no path found"),
+        Some(very_best) => println!("aoc12b: {}", very_best),
     }
 
-    println!("aoc12b: {}", very_best);
-
     Ok(())
 }
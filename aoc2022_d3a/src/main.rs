@@ -1,15 +1,279 @@
 // AoC 2022 day 3
 
-use std::collections::HashSet;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashSet};
+
+// the priority of an item type: a-z are 1-26, A-Z are 27-52
+fn priority(c: char) -> u32 {
+    if c.is_ascii_lowercase() {
+        c as u32 - b'a' as u32 + 1
+    } else {
+        c as u32 - b'A' as u32 + 27
+    }
+}
+
+// one exchange of an item in the first compartment with one in the second
+// positions are indices into the whole rucksack string
+#[derive(Debug, Clone, Copy)]
+struct Swap {
+    first_pos: usize,
+    first_item: char,
+    second_pos: usize,
+    second_item: char,
+}
+
+// the reorganization plan for one rucksack
+#[derive(Debug)]
+struct SackPlan {
+    shared: Vec<char>,
+    swaps: Vec<Swap>,
+}
+
+// count the occurrences of each item type in a compartment
+fn item_counts(comp: &str) -> BTreeMap<char, usize> {
+    let mut counts = BTreeMap::new();
+    for c in comp.chars() {
+        *counts.entry(c).or_insert(0) += 1;
+    }
+    counts
+}
+
+// which totals up to max can be made from whole item types in `candidates`
+fn reachable_sums(candidates: &[(char, usize)], max: usize) -> Vec<bool> {
+    let mut reached = vec![false; max + 1];
+    reached[0] = true;
+    for &(_, cnt) in candidates {
+        for s in (cnt..=max).rev() {
+            reached[s] = reached[s] || reached[s - cnt];
+        }
+    }
+    reached
+}
+
+// pick whole item types from `candidates` whose counts sum to exactly `target`
+// returns None if no such subset exists
+fn fill_moves(candidates: &[(char, usize)], target: usize) -> Option<Vec<char>> {
+    // reach[s] holds the index of the candidate that first reached sum s
+    let mut reach: Vec<Option<usize>> = vec![None; target + 1];
+    let mut reached = vec![false; target + 1];
+    reached[0] = true;
+    for (i, &(_, cnt)) in candidates.iter().enumerate() {
+        for s in (cnt..=target).rev() {
+            if !reached[s] && reached[s - cnt] {
+                reached[s] = true;
+                reach[s] = Some(i);
+            }
+        }
+    }
+    if !reached[target] {
+        return None;
+    }
+
+    let mut chosen = vec![];
+    let mut s = target;
+    while s > 0 {
+        let i = reach[s]?;
+        chosen.push(candidates[i].0);
+        s -= candidates[i].1;
+    }
+    Some(chosen)
+}
+
+// work out the fewest swaps that leave no item type in both compartments
+// every copy of a shared type is gathered on one side; when the two directions
+// don't balance, whole types found only on one side are moved to make up the difference
+fn plan_sack(sack: &str) -> Result<SackPlan, String> {
+    if !sack.len().is_multiple_of(2) {
+        return Err(format!("rucksack {} has an odd number of items", sack));
+    }
+    let half = sack.len() / 2;
+    let (fs, ss) = sack.split_at(half);
+    let fcounts = item_counts(fs);
+    let scounts = item_counts(ss);
+
+    let shared: Vec<char> = fcounts
+        .keys()
+        .filter(|c| scounts.contains_key(c))
+        .copied()
+        .collect();
+    let first_only: Vec<(char, usize)> = fcounts
+        .iter()
+        .filter(|(c, _)| !scounts.contains_key(c))
+        .map(|(c, n)| (*c, *n))
+        .collect();
+    let second_only: Vec<(char, usize)> = scounts
+        .iter()
+        .filter(|(c, _)| !fcounts.contains_key(c))
+        .map(|(c, n)| (*c, *n))
+        .collect();
+
+    // choosing a side for each shared type only matters through how many items leave
+    // each compartment, so run a subset-sum over the shared types keyed on
+    // (items leaving the first, second compartment items of the types leaving it)
+    // pick[a][b] holds the index of the shared type that first reached that state
+    let btotal: usize = shared.iter().map(|c| scounts[c]).sum();
+    let mut pick: Vec<Vec<Option<usize>>> = vec![vec![None; btotal + 1]; half + 1];
+    let mut reached = vec![vec![false; btotal + 1]; half + 1];
+    reached[0][0] = true;
+    for (i, c) in shared.iter().enumerate() {
+        let (fa, sb) = (fcounts[c], scounts[c]);
+        for a in (fa..=half).rev() {
+            for b in (sb..=btotal).rev() {
+                if !reached[a][b] && reached[a - fa][b - sb] {
+                    reached[a][b] = true;
+                    pick[a][b] = Some(i);
+                }
+            }
+        }
+    }
+
+    // the cheapest state whose imbalance can be made up from one-sided types
+    // best holds (swap count, items leaving the first, second compartment items kept)
+    let first_fill = reachable_sums(&first_only, half);
+    let second_fill = reachable_sums(&second_only, half);
+    let mut best: Option<(usize, usize, usize)> = None;
+    for (a, row) in reached.iter().enumerate() {
+        for (b, _) in row.iter().enumerate().filter(|(_, &r)| r) {
+            // b items of the second compartment stay, the rest leave for the first
+            let (n_to_second, n_to_first) = (a, btotal - b);
+            let swaps = n_to_second.max(n_to_first);
+            let feasible = match n_to_second.cmp(&n_to_first) {
+                Ordering::Less => first_fill[n_to_first - n_to_second],
+                Ordering::Greater => second_fill[n_to_second - n_to_first],
+                Ordering::Equal => true,
+            };
+            if feasible && best.is_none_or(|bs| swaps < bs.0) {
+                best = Some((swaps, a, b));
+            }
+        }
+    }
+
+    let (_, mut a, mut b) = match best {
+        Some(bs) => bs,
+        None => {
+            return Err(format!(
+                "rucksack {} cannot be made disjoint by swapping",
+                sack
+            ))
+        }
+    };
+    let (n_to_second, n_to_first) = (a, btotal - b);
+
+    // walk the picks back to find which shared types go to the second compartment
+    let mut to_second = vec![];
+    while a > 0 || b > 0 {
+        let c = shared[pick[a][b].ok_or("broken subset-sum trail")?];
+        to_second.push(c);
+        a -= fcounts[&c];
+        b -= scounts[&c];
+    }
+    let mut to_first: Vec<char> = shared
+        .iter()
+        .filter(|c| !to_second.contains(c))
+        .copied()
+        .collect();
+    if n_to_second < n_to_first {
+        to_second.extend(fill_moves(&first_only, n_to_first - n_to_second).unwrap_or_default());
+    } else if n_to_first < n_to_second {
+        to_first.extend(fill_moves(&second_only, n_to_second - n_to_first).unwrap_or_default());
+    }
+
+    let leaving_first = fs
+        .char_indices()
+        .filter(|(_, c)| to_second.contains(c))
+        .collect::<Vec<(usize, char)>>();
+    let leaving_second = ss
+        .char_indices()
+        .filter(|(_, c)| to_first.contains(c))
+        .map(|(i, c)| (i + half, c))
+        .collect::<Vec<(usize, char)>>();
+    let swaps = leaving_first
+        .iter()
+        .zip(leaving_second.iter())
+        .map(|(&(fp, fc), &(sp, sc))| Swap {
+            first_pos: fp,
+            first_item: fc,
+            second_pos: sp,
+            second_item: sc,
+        })
+        .collect();
+
+    Ok(SackPlan { shared, swaps })
+}
+
+// the item types common to every rucksack in a group
+fn badge_candidates(group: &[&str]) -> Vec<char> {
+    let mut common: HashSet<char> = group[0].chars().collect();
+    for s in &group[1..] {
+        let hs: HashSet<char> = s.chars().collect();
+        common.retain(|c| hs.contains(c));
+    }
+    let mut v: Vec<char> = common.into_iter().collect();
+    v.sort_unstable();
+    v
+}
+
+// print the swap plan for every rucksack followed by any badge inconsistencies
+fn print_plan(sacks: &[&str]) {
+    for (i, s) in sacks.iter().enumerate() {
+        match plan_sack(s) {
+            Ok(plan) if plan.shared.is_empty() => println!("sack {}: already disjoint", i + 1),
+            Ok(plan) => {
+                let shared: String = plan.shared.iter().collect();
+                println!(
+                    "sack {}: shared {}, {} swap(s)",
+                    i + 1,
+                    shared,
+                    plan.swaps.len()
+                );
+                for sw in plan.swaps {
+                    println!(
+                        "    swap {}@{} <-> {}@{}",
+                        sw.first_item, sw.first_pos, sw.second_item, sw.second_pos
+                    );
+                }
+            }
+            Err(e) => println!("sack {}: {}", i + 1, e),
+        }
+    }
+
+    for (g, group) in sacks.chunks(3).enumerate() {
+        if group.len() < 3 {
+            println!(
+                "group {}: incomplete, only {} rucksack(s)",
+                g + 1,
+                group.len()
+            );
+            continue;
+        }
+        let cands = badge_candidates(group);
+        match cands.len() {
+            1 => {}
+            0 => println!("group {}: no badge candidate", g + 1),
+            _ => println!(
+                "group {}: several badge candidates {}",
+                g + 1,
+                cands.iter().collect::<String>()
+            ),
+        }
+    }
+}
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let input = include_str!("../../rucksacks.dat");
 
     let sacks = input.trim().split('\n').collect::<Vec<&str>>();
+
+    // "plan" argument prints the reorganization planner report instead
+    if std::env::args().nth(1).as_deref() == Some("plan") {
+        print_plan(&sacks);
+        return Ok(());
+    }
+
     let mut ansa: u32 = 0;
 
     let mut ahs: Vec<HashSet<char>> = vec![HashSet::new(); 3];
-    let mut idx = 0;  // 1, 2 or 3 for each elf in a group
+    let mut idx = 0; // 1, 2 or 3 for each elf in a group
     let mut ansb: u32 = 0;
 
     for s in sacks {
@@ -25,14 +289,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
         for c in ss.chars() {
             sshs.insert(c);
             ahs[idx].insert(c);
-        } 
+        }
 
         for c in fshs.intersection(&sshs) {
-            if *c as u8 >= b'a' && *c as u8 <= b'z' {
-                ansa += *c as u32 - b'a' as u32 + 1;
-            } else {
-                ansa += *c as u32 - b'A' as u32 + 27;
-            }
+            ansa += priority(*c);
         }
 
         if idx == 2 {
@@ -40,11 +300,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             let isect12: HashSet<_> = ahs[1].intersection(&ahs[2]).collect();
             let intersection: HashSet<_> = isect01.intersection(&isect12).collect();
             for c in intersection {
-                if **c as u8 >= b'a' && **c as u8 <= b'z' {
-                    ansb += **c as u32 - b'a' as u32 + 1;
-                } else {
-                    ansb += **c as u32 - b'A' as u32 + 27;
-                }
+                ansb += priority(**c);
             }
         }
 
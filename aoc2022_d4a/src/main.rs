@@ -1,29 +1,334 @@
 // AoC 2022 day 4
 
+// an inclusive range of section ids
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Interval {
+    lo: u32,
+    hi: u32,
+}
+
+impl Interval {
+    fn new(lo: u32, hi: u32) -> Self {
+        Self { lo, hi }
+    }
+}
+
+impl std::fmt::Display for Interval {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}-{}", self.lo, self.hi)
+    }
+}
+
+// a set of sections kept as sorted, disjoint, non-adjacent intervals
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct IntervalSet {
+    ivs: Vec<Interval>,
+}
+
+impl IntervalSet {
+    fn new() -> Self {
+        Self { ivs: vec![] }
+    }
+
+    fn from_interval(iv: Interval) -> Self {
+        Self { ivs: vec![iv] }
+    }
+
+    fn is_empty(&self) -> bool {
+        self.ivs.is_empty()
+    }
+
+    // number of sections in the set
+    fn len(&self) -> u64 {
+        self.ivs
+            .iter()
+            .map(|iv| iv.hi as u64 - iv.lo as u64 + 1)
+            .sum()
+    }
+
+    // add an interval, merging it with any it touches or overlaps
+    fn merge(&mut self, iv: Interval) {
+        let mut lo = iv.lo;
+        let mut hi = iv.hi;
+        let mut out = Vec::with_capacity(self.ivs.len() + 1);
+        let mut placed = false;
+        for cur in &self.ivs {
+            if cur.hi.saturating_add(1) < lo {
+                out.push(*cur);
+            } else if hi.saturating_add(1) < cur.lo {
+                if !placed {
+                    out.push(Interval::new(lo, hi));
+                    placed = true;
+                }
+                out.push(*cur);
+            } else {
+                lo = lo.min(cur.lo);
+                hi = hi.max(cur.hi);
+            }
+        }
+        if !placed {
+            out.push(Interval::new(lo, hi));
+        }
+        self.ivs = out;
+    }
+
+    fn union(&self, other: &IntervalSet) -> IntervalSet {
+        let mut u = self.clone();
+        for iv in &other.ivs {
+            u.merge(*iv);
+        }
+        u
+    }
+
+    fn intersection(&self, other: &IntervalSet) -> IntervalSet {
+        let mut isect = IntervalSet::new();
+        let (mut i, mut j) = (0, 0);
+        while i < self.ivs.len() && j < other.ivs.len() {
+            let a = self.ivs[i];
+            let b = other.ivs[j];
+            let lo = a.lo.max(b.lo);
+            let hi = a.hi.min(b.hi);
+            if lo <= hi {
+                isect.ivs.push(Interval::new(lo, hi));
+            }
+            if a.hi < b.hi {
+                i += 1;
+            } else {
+                j += 1;
+            }
+        }
+        isect
+    }
+
+    // true if every section of other is also in self
+    fn contains(&self, other: &IntervalSet) -> bool {
+        self.intersection(other) == *other
+    }
+
+    fn overlaps(&self, other: &IntervalSet) -> bool {
+        !self.intersection(other).is_empty()
+    }
+
+    // sections between the lowest and highest members that are not in the set
+    fn gaps(&self) -> IntervalSet {
+        IntervalSet {
+            ivs: self
+                .ivs
+                .windows(2)
+                .map(|w| Interval::new(w[0].hi + 1, w[1].lo - 1))
+                .collect(),
+        }
+    }
+}
+
+impl std::fmt::Display for IntervalSet {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let s = self
+            .ivs
+            .iter()
+            .map(|iv| iv.to_string())
+            .collect::<Vec<String>>()
+            .join(",");
+        write!(f, "{}", s)
+    }
+}
+
+// parse a line of any number of comma separated "lo-hi" ranges, one per elf
+fn parse_crew(line: &str) -> Result<Vec<IntervalSet>, String> {
+    let mut crew = vec![];
+    for r in line.split(',') {
+        let (lo, hi) = match r.trim().split_once('-') {
+            Some(b) => b,
+            None => return Err(format!("bad range '{}' in line '{}'", r, line)),
+        };
+        let lo = lo
+            .parse::<u32>()
+            .map_err(|e| format!("bad lower bound in '{}': {}", r, e))?;
+        let hi = hi
+            .parse::<u32>()
+            .map_err(|e| format!("bad upper bound in '{}': {}", r, e))?;
+        if lo > hi {
+            return Err(format!("range '{}' has its bounds reversed", r));
+        }
+        crew.push(IntervalSet::from_interval(Interval::new(lo, hi)));
+    }
+    Ok(crew)
+}
+
+// the index pairs of elves whose assignments overlap
+fn overlapping_pairs(crew: &[IntervalSet]) -> Vec<(usize, usize)> {
+    let mut pairs = vec![];
+    for i in 0..crew.len() {
+        for j in (i + 1)..crew.len() {
+            if crew[i].overlaps(&crew[j]) {
+                pairs.push((i, j));
+            }
+        }
+    }
+    pairs
+}
+
+// sweep over the start and end points of every interval, returning the
+// consecutive (lo, hi, depth) segments where depth is the number of covering intervals,
+// with touching segments of the same depth merged
+fn coverage_segments<'a, I>(ivs: I) -> Vec<(u32, u32, usize)>
+where
+    I: IntoIterator<Item = &'a Interval>,
+{
+    // +1 at each start, -1 just past each end, ends sorted before starts at the same point
+    let mut events: Vec<(u64, i32)> = vec![];
+    for iv in ivs {
+        events.push((iv.lo as u64, 1));
+        events.push((iv.hi as u64 + 1, -1));
+    }
+    events.sort_unstable();
+
+    let mut segs: Vec<(u32, u32, usize)> = vec![];
+    let mut depth = 0;
+    for (i, &(pos, delta)) in events.iter().enumerate() {
+        depth += delta;
+        if let Some(&(next, _)) = events.get(i + 1) {
+            if depth > 0 && next > pos {
+                let (lo, hi, d) = (pos as u32, (next - 1) as u32, depth as usize);
+                match segs.last_mut() {
+                    Some(prev) if prev.1 + 1 == lo && prev.2 == d => prev.1 = hi,
+                    _ => segs.push((lo, hi, d)),
+                }
+            }
+        }
+    }
+    segs
+}
+
+// the largest number of elves assigned to any single section
+fn max_overlap_depth(crew: &[IntervalSet]) -> usize {
+    coverage_segments(crew.iter().flat_map(|s| s.ivs.iter()))
+        .iter()
+        .map(|&(_, _, depth)| depth)
+        .max()
+        .unwrap_or(0)
+}
+
+// print a histogram of how many elves clean each section over the whole file,
+// the most redundantly cleaned span and the sections cleaned by exactly one elf
+fn print_coverage_report(lines: &[&str], csv: bool) -> Result<(), String> {
+    let mut all = vec![];
+    for l in lines {
+        for set in parse_crew(l)? {
+            all.extend(set.ivs);
+        }
+    }
+    let segs = coverage_segments(&all);
+
+    // hist[d] is the number of sections cleaned by exactly d elves
+    let max_depth = segs.iter().map(|&(_, _, d)| d).max().unwrap_or(0);
+    let mut hist = vec![0_u64; max_depth + 1];
+    let mut single = IntervalSet::new();
+    for &(lo, hi, d) in &segs {
+        hist[d] += hi as u64 - lo as u64 + 1;
+        if d == 1 {
+            single.merge(Interval::new(lo, hi));
+        }
+    }
+    // sections between the lowest and highest assignment that nobody cleans
+    if let (Some(first), Some(last)) = (segs.first(), segs.last()) {
+        hist[0] = last.1 as u64 - first.0 as u64 + 1 - hist.iter().sum::<u64>();
+    }
+
+    if csv {
+        println!("elves,sections");
+        for (d, n) in hist.iter().enumerate() {
+            println!("{},{}", d, n);
+        }
+        return Ok(());
+    }
+
+    println!("{:>6} | {:>9}", "elves", "sections");
+    println!("{:->6}-+-{:->9}", "", "");
+    for (d, n) in hist.iter().enumerate() {
+        println!("{:>6} | {:>9}", d, n);
+    }
+
+    // the longest span among those cleaned by the most elves
+    if let Some(&(lo, hi, d)) = segs
+        .iter()
+        .filter(|&&(_, _, d)| d == max_depth)
+        .max_by_key(|&&(lo, hi, _)| (hi - lo, std::cmp::Reverse(lo)))
+    {
+        println!(
+            "most redundant span: {} cleaned by {} elves",
+            Interval::new(lo, hi),
+            d
+        );
+    }
+    println!(
+        "cleaned by exactly one elf: {} sections [{}]",
+        single.len(),
+        single
+    );
+
+    Ok(())
+}
+
+// print the pairwise overlaps, overlap depth and uncovered sections for each crew
+fn print_crew_report(lines: &[&str]) -> Result<(), String> {
+    for (n, l) in lines.iter().enumerate() {
+        let crew = parse_crew(l)?;
+        let covered = crew.iter().fold(IntervalSet::new(), |u, s| u.union(s));
+        let pairs = overlapping_pairs(&crew)
+            .iter()
+            .map(|(i, j)| format!("{}&{}", i + 1, j + 1))
+            .collect::<Vec<String>>()
+            .join(" ");
+        println!(
+            "crew {}: {} elves covering {} sections, overlapping pairs [{}], max depth {}, uncovered [{}]",
+            n + 1,
+            crew.len(),
+            covered.len(),
+            pairs,
+            max_overlap_depth(&crew),
+            covered.gaps()
+        );
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let input = include_str!("../../cleaning_sectors.dat");
 
-    let pairs = input.trim().split('\n').collect::<Vec<&str>>();
+    let crews = input.trim().split('\n').collect::<Vec<&str>>();
+
+    // "crew" argument prints a per-line report for crews of any size
+    if std::env::args().nth(1).as_deref() == Some("crew") {
+        print_crew_report(&crews)?;
+        return Ok(());
+    }
+
+    // "coverage" argument prints the sweep line report over every assignment, "--csv" as CSV
+    if std::env::args().nth(1).as_deref() == Some("coverage") {
+        let csv = std::env::args().nth(2).as_deref() == Some("--csv");
+        print_coverage_report(&crews, csv)?;
+        return Ok(());
+    }
+
     let mut ansa: u32 = 0;
     let mut ansb: u32 = 0;
 
-    for p in pairs {
-        // v1 is a vec of the two ranges, one for each elf
-        let v1: Vec<&str> = p.split(',').collect();
+    for l in crews {
+        let crew = parse_crew(l)?;
 
-        // e0 is the range for the first elf, etc.
-        let e0: Vec<&str> = v1[0].split('-').collect();
-        let e1: Vec<&str> = v1[1].split('-').collect();
-
-        // the lower and upper bounds of the two ranges
-        let e0lb = e0[0].parse::<u32>().unwrap();
-        let e0ub = e0[1].parse::<u32>().unwrap();
-        let e1lb = e1[0].parse::<u32>().unwrap();
-        let e1ub = e1[1].parse::<u32>().unwrap();
+        let mut contained = false;
+        for i in 0..crew.len() {
+            for j in 0..crew.len() {
+                if i != j && crew[i].contains(&crew[j]) {
+                    contained = true;
+                }
+            }
+        }
 
-        if e0lb >= e1lb && e0ub <= e1ub || e1lb >= e0lb && e1ub <= e0ub {
+        if contained {
             ansa += 1; // fully overlap
-        } else if e0lb <= e1lb && e0ub >= e1lb || e1lb <= e0lb && e1ub >= e0lb {
+        } else if !overlapping_pairs(&crew).is_empty() {
             ansb += 1; // any overlap except fully
         }
     }
@@ -1,66 +1,371 @@
 // AoC 2022 day 5
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let input = include_str!("../../crate_stacks.dat");
-    const NUMSTACKS: usize = 9;
-    let mut vsa: Vec<Vec<char>> = vec![vec![]; NUMSTACKS];
-    let mut vsb: Vec<Vec<char>> = vec![vec![]; NUMSTACKS];
-    let mut collecting_stacks = true;
-
-    let coms = input.split('\n').collect::<Vec<&str>>();
-
-    for com in coms {
-        if com.is_empty() {
-            collecting_stacks = false;
-        } else if collecting_stacks {
-            // building the crate stacks
-            for (i, c) in com.chars().enumerate() {
-                if i > 4 * NUMSTACKS + 2 {
-                    break;
-                }
-                if i % 4 == 1 {
-                    if c == '1' {
-                        break;
-                    } else if c != ' ' {
-                        vsa[i / 4].insert(0, c);
-                        vsb[i / 4].insert(0, c);
-                    }
-                }
+// a crate stack, bottom crate first
+type Stack = Vec<String>;
+
+// one "move n from a to b" command with 0-based stack indices
+#[derive(Debug, Clone)]
+struct Move {
+    num: usize,
+    from: usize,
+    to: usize,
+    line: usize, // 1-based line number in the input
+    com: String,
+}
+
+// read the stack diagram, using the numbered footer line to find how many stacks
+// there are and which columns they occupy
+fn parse_diagram(lines: &[&str]) -> Result<Vec<Stack>, String> {
+    let (footer, rows) = match lines.split_last() {
+        Some(fr) => fr,
+        None => return Err("empty stack diagram".to_string()),
+    };
+
+    // the column span of each stack label in the footer
+    let mut cols: Vec<(usize, usize)> = vec![];
+    for (start, label) in tokens(footer) {
+        let num = label
+            .parse::<usize>()
+            .map_err(|_| format!("bad stack label '{}' in diagram footer", label))?;
+        if num != cols.len() + 1 {
+            return Err(format!(
+                "stack label {} out of order in diagram footer",
+                num
+            ));
+        }
+        cols.push((start, start + label.len() - 1));
+    }
+    if cols.is_empty() {
+        return Err("no stack labels in diagram footer".to_string());
+    }
+
+    let mut stacks: Vec<Stack> = vec![vec![]; cols.len()];
+    for (row, l) in rows.iter().enumerate().rev() {
+        for (start, tok) in tokens(l) {
+            let end = start + tok.len() - 1;
+            let label = match tok.strip_prefix('[').and_then(|t| t.strip_suffix(']')) {
+                Some(lbl) if !lbl.is_empty() => lbl,
+                _ => return Err(format!("bad crate '{}' on line {}", tok, row + 1)),
+            };
+            let idx = cols
+                .iter()
+                .position(|&(lo, hi)| lo <= end && start <= hi)
+                .ok_or(format!(
+                    "crate [{}] on line {} is not above a stack",
+                    label,
+                    row + 1
+                ))?;
+            if stacks[idx].len() != rows.len() - 1 - row {
+                return Err(format!("crate [{}] on line {} is floating", label, row + 1));
             }
-        } else {
-            // executing commands
-            let w = com.split(' ').collect::<Vec<&str>>();
-            let num = w[1].parse::<usize>().unwrap();
-            let from = w[3].parse::<usize>().unwrap() - 1;
-            let dest = w[5].parse::<usize>().unwrap() - 1;
-            let ins_idx = vsb[dest].len();
-
-            for _ in 0..num {
-                if let Some(elem) = vsa[from].pop() {
-                    vsa[dest].push(elem);
-                }
-                if let Some(elem) = vsb[from].pop() {
-                    vsb[dest].insert(ins_idx, elem);
-                }
+            stacks[idx].push(label.to_string());
+        }
+    }
+
+    Ok(stacks)
+}
+
+// split a line into (starting column, text) runs of non-space characters
+fn tokens(l: &str) -> Vec<(usize, &str)> {
+    let mut toks = vec![];
+    let mut start = None;
+    for (i, c) in l.char_indices() {
+        match (c != ' ', start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                toks.push((s, &l[s..i]));
+                start = None;
             }
+            _ => {}
         }
     }
+    if let Some(s) = start {
+        toks.push((s, &l[s..]));
+    }
+    toks
+}
 
-    let mut ans: String = "".to_string();
-    for stack in vsa.iter_mut().take(NUMSTACKS) {
-        if let Some(elem) = stack.pop() {
-            ans.push(elem);
+// parse a "move n from a to b" line, checking the stacks exist
+fn parse_move(com: &str, line: usize, numstacks: usize) -> Result<Move, String> {
+    let w = com.split_whitespace().collect::<Vec<&str>>();
+    if w.len() != 6 || w[0] != "move" || w[2] != "from" || w[4] != "to" {
+        return Err(format!("line {}: bad command '{}'", line, com));
+    }
+    let mut nums = [0; 3];
+    for (n, s) in nums.iter_mut().zip([w[1], w[3], w[5]]) {
+        *n = s
+            .parse::<usize>()
+            .map_err(|e| format!("line {}: bad number '{}': {}", line, s, e))?;
+    }
+    for s in &nums[1..] {
+        if *s == 0 || *s > numstacks {
+            return Err(format!(
+                "line {}: '{}' references stack {} but there are only {}",
+                line, com, s, numstacks
+            ));
         }
     }
-    println!("aoc5a: {}", ans);
 
-    ans = "".to_string();
-    for stack in vsb.iter_mut().take(NUMSTACKS) {
-        if let Some(elem) = stack.pop() {
-            ans.push(elem);
+    Ok(Move {
+        num: nums[0],
+        from: nums[1] - 1,
+        to: nums[2] - 1,
+        line,
+        com: com.to_string(),
+    })
+}
+
+// split the input into its stack diagram and list of moves
+fn parse_input(input: &str) -> Result<(Vec<Stack>, Vec<Move>), String> {
+    let lines = input.lines().collect::<Vec<&str>>();
+    let blank = lines
+        .iter()
+        .position(|l| l.trim().is_empty())
+        .ok_or("no blank line after the stack diagram")?;
+
+    let stacks = parse_diagram(&lines[..blank])?;
+    let mut moves = vec![];
+    for (i, com) in lines.iter().enumerate().skip(blank + 1) {
+        if !com.trim().is_empty() {
+            moves.push(parse_move(com, i + 1, stacks.len())?);
         }
     }
-    println!("aoc5b: {}", ans);
+
+    Ok((stacks, moves))
+}
+
+// the labels of the top crates of each stack
+fn top_crates(stacks: &[Stack]) -> String {
+    stacks.iter().filter_map(|s| s.last()).cloned().collect()
+}
+
+// a crane model that moves crates between stacks
+trait Crane {
+    fn name(&self) -> &str;
+
+    // carry out m, the source stack is known to hold at least m.num crates
+    fn apply(&self, stacks: &mut [Stack], m: &Move);
+
+    // reverse a previous apply of m
+    fn undo(&self, stacks: &mut [Stack], m: &Move);
+}
+
+// moves crates one at a time, reversing their order
+struct CrateMover9000;
+
+impl Crane for CrateMover9000 {
+    fn name(&self) -> &str {
+        "CrateMover 9000"
+    }
+
+    fn apply(&self, stacks: &mut [Stack], m: &Move) {
+        let at = stacks[m.from].len() - m.num;
+        let moved = stacks[m.from].split_off(at);
+        stacks[m.to].extend(moved.into_iter().rev());
+    }
+
+    fn undo(&self, stacks: &mut [Stack], m: &Move) {
+        let at = stacks[m.to].len() - m.num;
+        let moved = stacks[m.to].split_off(at);
+        stacks[m.from].extend(moved.into_iter().rev());
+    }
+}
+
+// moves several crates at once, keeping their order
+struct CrateMover9001;
+
+impl Crane for CrateMover9001 {
+    fn name(&self) -> &str {
+        "CrateMover 9001"
+    }
+
+    fn apply(&self, stacks: &mut [Stack], m: &Move) {
+        let at = stacks[m.from].len() - m.num;
+        let moved = stacks[m.from].split_off(at);
+        stacks[m.to].extend(moved);
+    }
+
+    fn undo(&self, stacks: &mut [Stack], m: &Move) {
+        let at = stacks[m.to].len() - m.num;
+        let moved = stacks[m.to].split_off(at);
+        stacks[m.from].extend(moved);
+    }
+}
+
+// select a crane model by its number
+fn crane_model(model: &str) -> Result<Box<dyn Crane>, String> {
+    match model {
+        "9000" => Ok(Box::new(CrateMover9000)),
+        "9001" => Ok(Box::new(CrateMover9001)),
+        _ => Err(format!("unknown crane model '{}'", model)),
+    }
+}
+
+// a log of moves that can be stepped through, undone and replayed
+struct Replay {
+    crane: Box<dyn Crane>,
+    stacks: Vec<Stack>,
+    moves: Vec<Move>,
+    done: usize, // number of moves applied so far
+}
+
+impl Replay {
+    fn new(crane: Box<dyn Crane>, stacks: Vec<Stack>, moves: Vec<Move>) -> Self {
+        Self {
+            crane,
+            stacks,
+            moves,
+            done: 0,
+        }
+    }
+
+    // a log positioned after its last move, with stacks holding the final layout
+    fn from_end(crane: Box<dyn Crane>, stacks: Vec<Stack>, moves: Vec<Move>) -> Self {
+        let done = moves.len();
+        Self {
+            crane,
+            stacks,
+            moves,
+            done,
+        }
+    }
+
+    // apply the next move, returns false if there are none left
+    fn step(&mut self) -> Result<bool, String> {
+        let m = match self.moves.get(self.done) {
+            Some(m) => m,
+            None => return Ok(false),
+        };
+        let held = self.stacks[m.from].len();
+        if held < m.num {
+            return Err(format!(
+                "line {}: '{}' moves {} crates but stack {} holds only {}",
+                m.line,
+                m.com,
+                m.num,
+                m.from + 1,
+                held
+            ));
+        }
+        self.crane.apply(&mut self.stacks, m);
+        self.done += 1;
+        Ok(true)
+    }
+
+    // reverse the last applied move, returns false if there are none
+    fn undo(&mut self) -> Result<bool, String> {
+        if self.done == 0 {
+            return Ok(false);
+        }
+        let m = &self.moves[self.done - 1];
+        let held = self.stacks[m.to].len();
+        if held < m.num {
+            return Err(format!(
+                "line {}: can't undo '{}', stack {} holds only {} crates",
+                m.line,
+                m.com,
+                m.to + 1,
+                held
+            ));
+        }
+        self.crane.undo(&mut self.stacks, m);
+        self.done -= 1;
+        Ok(true)
+    }
+
+    fn run(&mut self) -> Result<(), String> {
+        while self.step()? {}
+        Ok(())
+    }
+
+    // step or undo until exactly n moves have been applied
+    fn seek(&mut self, n: usize) -> Result<(), String> {
+        while self.done > n && self.undo()? {}
+        while self.done < n {
+            if !self.step()? {
+                return Err(format!("there are only {} moves", self.moves.len()));
+            }
+        }
+        Ok(())
+    }
+}
+
+// draw stacks in the same format that parse_diagram reads
+fn render_diagram(stacks: &[Stack]) -> String {
+    let width = stacks.iter().flatten().map(|c| c.len()).max().unwrap_or(1) + 2;
+    let height = stacks.iter().map(|s| s.len()).max().unwrap_or(0);
+    let mut out = String::new();
+    for row in (0..height).rev() {
+        let cells = stacks
+            .iter()
+            .map(|s| match s.get(row) {
+                Some(c) => format!("{:<width$}", format!("[{}]", c), width = width),
+                None => " ".repeat(width),
+            })
+            .collect::<Vec<String>>();
+        out += cells.join(" ").trim_end();
+        out.push('\n');
+    }
+    let footer = (1..=stacks.len())
+        .map(|n| format!("{:^width$}", n, width = width))
+        .collect::<Vec<String>>();
+    out += footer.join(" ").trim_end();
+    out.push('\n');
+    out
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let input = include_str!("../../crate_stacks.dat");
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // "reverse <model> <file>" reads a file in this same format whose diagram is the
+    // final layout, and prints the starting diagram that the moves rearrange into it
+    if args.first().map(|s| s.as_str()) == Some("reverse") {
+        let crane = crane_model(args.get(1).map_or("9000", |s| s.as_str()))?;
+        let path = args
+            .get(2)
+            .ok_or("reverse needs a file holding the final layout")?;
+        let (stacks, moves) = parse_input(&std::fs::read_to_string(path)?)?;
+        let mut replay = Replay::from_end(crane, stacks, moves);
+        replay.seek(0)?;
+        println!("{}", render_diagram(&replay.stacks));
+        for m in &replay.moves {
+            println!("{}", m.com);
+        }
+        return Ok(());
+    }
+
+    let (stacks, moves) = parse_input(input)?;
+
+    // "replay <model> [n]" renders the diagram after move n, or after every move
+    if args.first().map(|s| s.as_str()) == Some("replay") {
+        let crane = crane_model(args.get(1).map_or("9000", |s| s.as_str()))?;
+        println!("{}", crane.name());
+        let mut replay = Replay::new(crane, stacks, moves);
+        if let Some(n) = args.get(2) {
+            replay.seek(n.parse()?)?;
+            println!(
+                "after move {}:\n{}",
+                replay.done,
+                render_diagram(&replay.stacks)
+            );
+        } else {
+            println!("start:\n{}", render_diagram(&replay.stacks));
+            while replay.step()? {
+                let m = &replay.moves[replay.done - 1];
+                println!("{}:\n{}", m.com, render_diagram(&replay.stacks));
+            }
+        }
+        return Ok(());
+    }
+
+    let mut ra = Replay::new(Box::new(CrateMover9000), stacks.clone(), moves.clone());
+    ra.run()?;
+    println!("aoc5a: {}", top_crates(&ra.stacks));
+
+    let mut rb = Replay::new(Box::new(CrateMover9001), stacks, moves);
+    rb.run()?;
+    println!("aoc5b: {}", top_crates(&rb.stacks));
 
     Ok(())
 }
@@ -1,27 +1,176 @@
 // AoC 2022 day 6
 
-use std::collections::HashSet;
+use std::collections::VecDeque;
+use std::io::{BufRead, BufReader, Read};
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let input = include_str!("../../datastream.dat");
+// finds markers, runs of `size` all-different characters, in a datastream fed
+// to it one byte at a time, keeping a count of each byte in the current window
+struct MarkerDetector {
+    size: usize,
+    window: VecDeque<u8>,
+    counts: [usize; 256],
+    distinct: usize, // number of different bytes in the window
+    pos: usize,      // number of bytes processed so far
+}
+
+impl MarkerDetector {
+    fn new(size: usize) -> Self {
+        Self {
+            size,
+            window: VecDeque::with_capacity(size + 1),
+            counts: [0; 256],
+            distinct: 0,
+            pos: 0,
+        }
+    }
 
-    let ds = input.chars().collect::<Vec<char>>();
+    // forget the current window so the next marker can't overlap the last one
+    fn reset(&mut self) {
+        self.window.clear();
+        self.counts = [0; 256];
+        self.distinct = 0;
+    }
 
-    for (i, v) in ds.windows(4).enumerate() {
-        let hs: HashSet<char> = v.iter().copied().collect();
-        if hs.len() == 4 {
-            println!("aoc6a: {}", i + 4);
-            break;
+    // process the next byte, returning the marker position if it completes one
+    fn push(&mut self, b: u8) -> Option<usize> {
+        self.pos += 1;
+        self.window.push_back(b);
+        self.counts[b as usize] += 1;
+        if self.counts[b as usize] == 1 {
+            self.distinct += 1;
+        }
+        if self.window.len() > self.size {
+            let old = self.window.pop_front().unwrap() as usize;
+            self.counts[old] -= 1;
+            if self.counts[old] == 0 {
+                self.distinct -= 1;
+            }
+        }
+
+        if self.distinct == self.size {
+            Some(self.pos)
+        } else {
+            None
         }
     }
+}
 
-    for (i, v) in ds.windows(14).enumerate() {
-        let hs: HashSet<char> = v.iter().copied().collect();
-        if hs.len() == 14 {
-            println!("aoc6b: {}", i + 14);
+// the position of every marker of the given size in a datastream, skipping whitespace
+fn find_markers<R: Read>(r: R, size: usize) -> std::io::Result<Vec<usize>> {
+    let mut det = MarkerDetector::new(size);
+    let mut markers = vec![];
+    let mut rdr = BufReader::new(r);
+    loop {
+        let buf = rdr.fill_buf()?;
+        if buf.is_empty() {
             break;
         }
+        for &b in buf {
+            if !b.is_ascii_whitespace() {
+                markers.extend(det.push(b));
+            }
+        }
+        let len = buf.len();
+        rdr.consume(len);
+    }
+    Ok(markers)
+}
+
+// a frame of the datastream, the payload that follows a marker up to the next one
+#[derive(Debug)]
+struct Frame {
+    marker: usize, // offset of the first marker character
+    start: usize,  // offset of the first payload character
+    payload: String,
+}
+
+// split a datastream into frames delimited by non-overlapping markers of the given size
+fn decode_frames(ds: &str, size: usize, kind: &str) -> Result<Vec<Frame>, String> {
+    let bytes = ds.trim().as_bytes();
+    let mut det = MarkerDetector::new(size);
+    let mut starts = vec![];
+    for &b in bytes {
+        if let Some(pos) = det.push(b) {
+            starts.push(pos);
+            det.reset();
+        }
     }
+    if starts.is_empty() {
+        return Err(format!(
+            "datastream of {} characters never contains a {} marker ({} different characters in a row)",
+            bytes.len(),
+            kind,
+            size
+        ));
+    }
+
+    let mut frames = vec![];
+    for (i, &start) in starts.iter().enumerate() {
+        let end = starts.get(i + 1).map_or(bytes.len(), |next| next - size);
+        frames.push(Frame {
+            marker: start - size,
+            start,
+            payload: String::from_utf8_lossy(&bytes[start..end]).to_string(),
+        });
+    }
+    Ok(frames)
+}
+
+// print the offsets and payload of every frame
+fn print_frames(label: &str, frames: &[Frame]) {
+    for (i, f) in frames.iter().enumerate() {
+        println!(
+            "{} {}: marker at {}, payload at {}, length {}: {}",
+            label,
+            i + 1,
+            f.marker,
+            f.start,
+            f.payload.len(),
+            f.payload
+        );
+    }
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let input = include_str!("../../datastream.dat");
+
+    // "markers <size> [file]" lists every marker position in the file, or stdin
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|s| s.as_str()) == Some("markers") {
+        let size: usize = args.get(1).ok_or("markers needs a window size")?.parse()?;
+        if size == 0 {
+            return Err("window size must be at least 1".into());
+        }
+        let markers = match args.get(2) {
+            Some(path) => find_markers(std::fs::File::open(path)?, size)?,
+            None => find_markers(std::io::stdin(), size)?,
+        };
+        for m in markers {
+            println!("{}", m);
+        }
+        return Ok(());
+    }
+
+    // "decode [file]" splits the file, or stdin, into packets and messages
+    if args.first().map(|s| s.as_str()) == Some("decode") {
+        let ds = match args.get(1) {
+            Some(path) => std::fs::read_to_string(path)?,
+            None => {
+                let mut s = String::new();
+                std::io::stdin().read_to_string(&mut s)?;
+                s
+            }
+        };
+        print_frames("packet", &decode_frames(&ds, 4, "start-of-packet")?);
+        print_frames("message", &decode_frames(&ds, 14, "start-of-message")?);
+        return Ok(());
+    }
+
+    let packets = decode_frames(input, 4, "start-of-packet")?;
+    println!("aoc6a: {}", packets[0].start);
+
+    let messages = decode_frames(input, 14, "start-of-message")?;
+    println!("aoc6b: {}", messages[0].start);
 
     Ok(())
 }
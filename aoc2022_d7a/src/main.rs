@@ -1,92 +1,669 @@
 // AoC 2022 day 7
 
-use std::collections::HashMap;
-
-// add the passed size to all containing directories
-fn store_directory_size(
-    ds: &mut [usize],
-    size: usize,
-    hm: &HashMap<String, usize>,
-    path: &[String],
-) -> Result<(), String> {
-    let mut dirname = "".to_string();
-    for dir in path {
-        dirname += dir;
-        if let Some(idx) = hm.get(&dirname) {
-            ds[*idx] += size;
-        } else {
-            return Err(format!("Directory name not found: {}", dirname));
-        }
-    }
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Kind {
+    Dir,
+    File(usize),
+}
 
-    Ok(())
+// a directory or file, children are indices into the FileSystem nodes
+#[derive(Debug)]
+struct Node {
+    name: String,
+    kind: Kind,
+    parent: Option<usize>,
+    children: Vec<usize>,
 }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let input = include_str!("../../directory_instrs.dat");
-    let mut hm: HashMap<String, usize> = HashMap::new();
-    let mut path: Vec<String> = vec![];
-    let mut file_sum = 0;
-    let mut dirsize: Vec<usize> = vec![];
+// the filesystem described by a transcript, node 0 is the root directory
+// nodes are always created after their parent so a parent's index is lower than its children's
+#[derive(Debug)]
+struct FileSystem {
+    nodes: Vec<Node>,
+}
 
-    // register the root directory
-    hm.insert("/".to_string(), 0);
-    dirsize.push(0);
+impl FileSystem {
+    fn new() -> Self {
+        Self {
+            nodes: vec![Node {
+                name: "/".to_string(),
+                kind: Kind::Dir,
+                parent: None,
+                children: vec![],
+            }],
+        }
+    }
 
-    let coms = input.split('\n').collect::<Vec<&str>>();
+    // the child of dir with the given name
+    fn child(&self, dir: usize, name: &str) -> Option<usize> {
+        self.nodes[dir]
+            .children
+            .iter()
+            .copied()
+            .find(|&c| self.nodes[c].name == name)
+    }
 
-    for com in coms {
-        if com.is_empty() {
-            continue;
+    // add an entry to dir, listing the same entry again is not an error
+    fn add(&mut self, dir: usize, name: &str, kind: Kind) -> Result<usize, String> {
+        if let Some(c) = self.child(dir, name) {
+            if self.nodes[c].kind != kind {
+                return Err(format!(
+                    "{} listed as both {:?} and {:?}",
+                    self.path(c),
+                    self.nodes[c].kind,
+                    kind
+                ));
+            }
+            return Ok(c);
         }
+        let idx = self.nodes.len();
+        self.nodes.push(Node {
+            name: name.to_string(),
+            kind,
+            parent: Some(dir),
+            children: vec![],
+        });
+        self.nodes[dir].children.push(idx);
+        Ok(idx)
+    }
 
-        let wordv = com.split(' ').collect::<Vec<&str>>();
-        match wordv[0] {
-            "$" => {
-                // command expected save dir size
-                if file_sum > 0 {
-                    store_directory_size(&mut dirsize, file_sum, &hm, &path)?;
-                    file_sum = 0;
-                }
-                match wordv[1] {
-                    "cd" => match wordv[2] {
-                        "/" => {
-                            path.clear();
-                            path.push("/".to_string());
-                        }
-                        ".." => {
-                            path.pop().unwrap();
-                        }
+    // build the tree from a "$ cd" / "$ ls" transcript
+    fn from_transcript(input: &str) -> Result<Self, String> {
+        let mut fs = FileSystem::new();
+        let mut cwd = 0;
+
+        for (i, com) in input.lines().enumerate() {
+            if com.is_empty() {
+                continue;
+            }
+
+            // names run to the end of the line so they may contain spaces
+            if let Some(name) = com.strip_prefix("$ cd ") {
+                match name {
+                    "/" => cwd = 0,
+                    ".." => {
+                        cwd = fs.nodes[cwd]
+                            .parent
+                            .ok_or(format!("line {}: cd .. from the root directory", i + 1))?
+                    }
+                    _ => match fs.child(cwd, name) {
+                        Some(c) if fs.nodes[c].kind == Kind::Dir => cwd = c,
                         _ => {
-                            let dirname = wordv[2].to_string() + "/";
-                            path.push(dirname);
+                            return Err(format!(
+                                "line {}: cd into unknown directory {}{}",
+                                i + 1,
+                                fs.dir_path(cwd),
+                                name
+                            ))
                         }
                     },
+                }
+            } else if com == "$ ls" {
+                // the listing follows on the next lines
+            } else if let Some(name) = com.strip_prefix("dir ") {
+                fs.add(cwd, name, Kind::Dir)?;
+            } else if let Some((size, name)) = com.split_once(' ') {
+                let size = size
+                    .parse::<usize>()
+                    .map_err(|e| format!("line {}: bad file size '{}': {}", i + 1, size, e))?;
+                fs.add(cwd, name, Kind::File(size))?;
+            } else {
+                return Err(format!("line {}: unrecognized line '{}'", i + 1, com));
+            }
+        }
+
+        Ok(fs)
+    }
+
+    // full path of a node, e.g. /a/e/i
+    fn path(&self, idx: usize) -> String {
+        let mut parts = vec![];
+        let mut cur = idx;
+        while let Some(p) = self.nodes[cur].parent {
+            parts.push(self.nodes[cur].name.as_str());
+            cur = p;
+        }
+        parts.reverse();
+        "/".to_string() + &parts.join("/")
+    }
+
+    // full path of a directory with a trailing slash
+    fn dir_path(&self, idx: usize) -> String {
+        let p = self.path(idx);
+        if idx == 0 {
+            p
+        } else {
+            p + "/"
+        }
+    }
+
+    // the total size of every node, files included, indexed like nodes
+    fn sizes(&self) -> Vec<usize> {
+        let mut sz: Vec<usize> = self
+            .nodes
+            .iter()
+            .map(|n| match n.kind {
+                Kind::File(s) => s,
+                Kind::Dir => 0,
+            })
+            .collect();
+        for idx in (1..self.nodes.len()).rev() {
+            let p = self.nodes[idx].parent.unwrap();
+            sz[p] += sz[idx];
+        }
+        sz
+    }
+
+    fn dirs(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.nodes.len()).filter(|&i| self.nodes[i].kind == Kind::Dir)
+    }
+
+    // du style listing, each directory's total size and path, children before parents
+    fn du(&self) -> String {
+        let sz = self.sizes();
+        let mut out = String::new();
+        self.du_node(0, &sz, &mut out);
+        out
+    }
+
+    fn du_node(&self, idx: usize, sz: &[usize], out: &mut String) {
+        for &c in &self.nodes[idx].children {
+            if self.nodes[c].kind == Kind::Dir {
+                self.du_node(c, sz, out);
+            }
+        }
+        *out += &format!("{}\t{}\n", sz[idx], self.path(idx));
+    }
+
+    // tree style rendering
+    fn tree(&self) -> String {
+        let sz = self.sizes();
+        let mut out = format!("/ (dir, {})\n", sz[0]);
+        self.tree_node(0, "", &sz, &mut out);
+        out
+    }
+
+    fn tree_node(&self, idx: usize, prefix: &str, sz: &[usize], out: &mut String) {
+        let kids = &self.nodes[idx].children;
+        for (i, &c) in kids.iter().enumerate() {
+            let last = i + 1 == kids.len();
+            let n = &self.nodes[c];
+            let desc = match n.kind {
+                Kind::Dir => format!("dir, {}", sz[c]),
+                Kind::File(s) => format!("file, {}", s),
+            };
+            *out += &format!(
+                "{}{} {} ({})\n",
+                prefix,
+                if last { "└──" } else { "├──" },
+                n.name,
+                desc
+            );
+            if n.kind == Kind::Dir {
+                let sub = prefix.to_string() + if last { "    " } else { "│   " };
+                self.tree_node(c, &sub, sz, out);
+            }
+        }
+    }
+
+    // nested JSON export of the whole tree
+    fn to_json(&self) -> String {
+        let sz = self.sizes();
+        let mut out = String::new();
+        self.json_node(0, &sz, &mut out);
+        out
+    }
+
+    fn json_node(&self, idx: usize, sz: &[usize], out: &mut String) {
+        let n = &self.nodes[idx];
+        let kind = match n.kind {
+            Kind::Dir => "dir",
+            Kind::File(_) => "file",
+        };
+        *out += &format!(
+            "{{\"name\":{},\"type\":\"{}\",\"size\":{}",
+            json_string(&n.name),
+            kind,
+            sz[idx]
+        );
+        if n.kind == Kind::Dir {
+            *out += ",\"children\":[";
+            for (i, &c) in n.children.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                self.json_node(c, sz, out);
+            }
+            out.push(']');
+        }
+        out.push('}');
+    }
+
+    // a transcript that lists every directory, in the format from_transcript reads
+    fn to_transcript(&self) -> String {
+        let mut out = "$ cd /\n".to_string();
+        self.transcript_node(0, &mut out);
+        out
+    }
+
+    fn transcript_node(&self, idx: usize, out: &mut String) {
+        *out += "$ ls\n";
+        for &c in &self.nodes[idx].children {
+            let n = &self.nodes[c];
+            match n.kind {
+                Kind::Dir => *out += &format!("dir {}\n", n.name),
+                Kind::File(s) => *out += &format!("{} {}\n", s, n.name),
+            }
+        }
+        for &c in &self.nodes[idx].children {
+            if self.nodes[c].kind == Kind::Dir {
+                *out += &format!("$ cd {}\n", self.nodes[c].name);
+                self.transcript_node(c, out);
+                *out += "$ cd ..\n";
+            }
+        }
+    }
+
+    // walk a real directory, entries sorted by name and symlinks skipped
+    fn from_disk(root: &std::path::Path) -> std::io::Result<Self> {
+        let mut fs = FileSystem::new();
+        fs.read_disk_dir(0, root)?;
+        Ok(fs)
+    }
+
+    fn read_disk_dir(&mut self, dir: usize, path: &std::path::Path) -> std::io::Result<()> {
+        let mut entries = std::fs::read_dir(path)?.collect::<std::io::Result<Vec<_>>>()?;
+        entries.sort_by_key(|e| e.file_name());
+        for e in entries {
+            let name = e.file_name().to_string_lossy().to_string();
+            // a transcript line ends at a line break so such names can't be written
+            if name.contains(['\n', '\r']) {
+                return Err(std::io::Error::other(format!(
+                    "{:?} can't be written to a transcript",
+                    e.path()
+                )));
+            }
+            let meta = std::fs::symlink_metadata(e.path())?;
+            if meta.is_dir() {
+                let c = self
+                    .add(dir, &name, Kind::Dir)
+                    .map_err(std::io::Error::other)?;
+                self.read_disk_dir(c, &e.path())?;
+            } else if meta.is_file() {
+                self.add(dir, &name, Kind::File(meta.len() as usize))
+                    .map_err(std::io::Error::other)?;
+            }
+        }
+        Ok(())
+    }
+
+    // create the tree in a new directory root, which must not already exist
+    fn materialize(&self, root: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+        std::fs::create_dir(root)?;
+        self.populate(root)
+    }
+
+    // create the tree under the existing empty directory root, files are sparse so
+    // they take little real space
+    fn populate(&self, root: &std::path::Path) -> Result<(), Box<dyn std::error::Error>> {
+        for idx in 1..self.nodes.len() {
+            let n = &self.nodes[idx];
+            if n.name.is_empty() || n.name == "." || n.name == ".." || n.name.contains('/') {
+                return Err(format!("can't create {} on disk", self.path(idx)).into());
+            }
+            let p = root.join(&self.path(idx)[1..]);
+            match n.kind {
+                Kind::Dir => std::fs::create_dir(&p)?,
+                Kind::File(s) => std::fs::File::create(&p)?.set_len(s as u64)?,
+            }
+        }
+        Ok(())
+    }
 
-                    &_ => {}
+    // directory path to total size, for comparing trees built in different orders
+    fn dir_totals(&self) -> std::collections::BTreeMap<String, usize> {
+        let sz = self.sizes();
+        self.dirs().map(|d| (self.path(d), sz[d])).collect()
+    }
+}
+
+// quote and escape a string for JSON
+fn json_string(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            c if (c as u32) < 0x20 => out += &format!("\\u{:04x}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// a set of non-nested nodes whose deletion frees enough space
+#[derive(Debug, Clone)]
+struct Cleanup {
+    freed: usize,
+    nodes: Vec<usize>,
+}
+
+// searches for the cleanups that waste the fewest bytes beyond what is needed
+struct CleanupPlanner<'a> {
+    fs: &'a FileSystem,
+    sizes: Vec<usize>,
+    cands: Vec<usize>,     // deletable nodes, largest first
+    remaining: Vec<usize>, // remaining[i] is the total size of cands[i..]
+    below: Vec<usize>,     // number of chosen nodes under each node
+    chosen: Vec<bool>,
+    needed: usize,
+    top: usize,
+    best: Vec<Cleanup>,
+    budget: usize, // search steps left before giving up on optimality
+}
+
+impl<'a> CleanupPlanner<'a> {
+    fn new(fs: &'a FileSystem, needed: usize, files: bool, top: usize) -> Self {
+        let sizes = fs.sizes();
+        let mut cands: Vec<usize> = (1..fs.nodes.len())
+            .filter(|&i| files || fs.nodes[i].kind == Kind::Dir)
+            .collect();
+        cands.sort_by_key(|&i| std::cmp::Reverse(sizes[i]));
+        let mut remaining = vec![0; cands.len() + 1];
+        for i in (0..cands.len()).rev() {
+            remaining[i] = remaining[i + 1] + sizes[cands[i]];
+        }
+
+        Self {
+            fs,
+            below: vec![0; sizes.len()],
+            chosen: vec![false; sizes.len()],
+            sizes,
+            cands,
+            remaining,
+            needed,
+            top,
+            best: vec![],
+            budget: 1_000_000,
+        }
+    }
+
+    // true if idx, one of its ancestors or one of its descendants is already chosen
+    fn nested(&self, idx: usize) -> bool {
+        if self.below[idx] > 0 || self.chosen[idx] {
+            return true;
+        }
+        let mut cur = idx;
+        while let Some(p) = self.fs.nodes[cur].parent {
+            if self.chosen[p] {
+                return true;
+            }
+            cur = p;
+        }
+        false
+    }
+
+    fn choose(&mut self, idx: usize, on: bool) {
+        self.chosen[idx] = on;
+        let mut cur = idx;
+        while let Some(p) = self.fs.nodes[cur].parent {
+            if on {
+                self.below[p] += 1;
+            } else {
+                self.below[p] -= 1;
+            }
+            cur = p;
+        }
+    }
+
+    // the waste that a new cleanup has to beat to be kept
+    fn bound(&self) -> usize {
+        if self.best.len() < self.top {
+            usize::MAX
+        } else {
+            self.best[self.top - 1].freed - self.needed
+        }
+    }
+
+    fn record(&mut self, freed: usize, picked: &[usize]) {
+        let mut nodes = picked.to_vec();
+        nodes.sort_unstable();
+        let pos = self
+            .best
+            .partition_point(|c| (c.freed, c.nodes.len()) <= (freed, nodes.len()));
+        self.best.insert(pos, Cleanup { freed, nodes });
+        self.best.truncate(self.top);
+    }
+
+    // try each remaining candidate from start onwards as the next deletion, skipping a
+    // candidate is just moving on to the next so only taking one recurses
+    fn search(&mut self, start: usize, freed: usize, picked: &mut Vec<usize>) {
+        for i in start..self.cands.len() {
+            if self.budget == 0 || freed + self.remaining[i] < self.needed {
+                return;
+            }
+            self.budget -= 1;
+
+            let idx = self.cands[i];
+            let total = freed + self.sizes[idx];
+            if total.saturating_sub(self.needed) <= self.bound() && !self.nested(idx) {
+                picked.push(idx);
+                if total >= self.needed {
+                    // adding more would only waste more
+                    self.record(total, picked);
+                } else {
+                    self.choose(idx, true);
+                    self.search(i + 1, total, picked);
+                    self.choose(idx, false);
                 }
+                picked.pop();
             }
+        }
+    }
+
+    // the best cleanups, ordered by bytes wasted then by number of deletions,
+    // and whether the search finished so they are known to be optimal
+    fn plan(mut self) -> (Vec<Cleanup>, bool) {
+        let mut picked = vec![];
+        self.search(0, 0, &mut picked);
+        (self.best, self.budget > 0)
+    }
+}
+
+// print the ranked cleanup plans for a disk of the given capacity that needs
+// required bytes free
+fn print_cleanup(
+    fs: &FileSystem,
+    capacity: usize,
+    required: usize,
+    files: bool,
+) -> Result<(), String> {
+    let used = fs.sizes()[0];
+    if used > capacity {
+        return Err(format!(
+            "{} bytes used is more than the capacity {}",
+            used, capacity
+        ));
+    }
+    if required > capacity {
+        return Err(format!(
+            "{} bytes required is more than the capacity {}",
+            required, capacity
+        ));
+    }
+    let needed = required.saturating_sub(capacity - used);
+    println!(
+        "used {} of {}, {} free, {} required, {} more needed",
+        used,
+        capacity,
+        capacity - used,
+        required,
+        needed
+    );
+    if needed == 0 {
+        println!("nothing needs deleting");
+        return Ok(());
+    }
+
+    let (plans, complete) = CleanupPlanner::new(fs, needed, files, 10).plan();
+    if plans.is_empty() {
+        return Err("no set of deletions frees enough space".to_string());
+    }
+    if !complete {
+        println!("search stopped early, these plans may not be the best");
+    }
+    for (rank, p) in plans.iter().enumerate() {
+        let paths = p
+            .nodes
+            .iter()
+            .map(|&n| fs.path(n))
+            .collect::<Vec<String>>()
+            .join(" ");
+        println!(
+            "{:>3}. wasted {:>10}, frees {:>10}: {}",
+            rank + 1,
+            p.freed - needed,
+            p.freed,
+            paths
+        );
+    }
+    Ok(())
+}
 
-            "dir" => {
-                // register a new directory path name with its index
-                let mut dirname = path.iter().cloned().collect::<String>();
-                dirname += wordv[1];
-                dirname += "/";
-                hm.insert(dirname, dirsize.len());
-                dirsize.push(0);
+// build the transcript's tree on disk in a scratch directory, read it back and
+// check that every directory total matches the transcript's
+// create a new, randomly named directory under the system temp directory
+fn scratch_dir() -> std::io::Result<std::path::PathBuf> {
+    use std::hash::{BuildHasher, Hasher};
+
+    for _ in 0..100 {
+        // each RandomState is seeded differently, so this is a fresh random number
+        let mut h = std::collections::hash_map::RandomState::new().build_hasher();
+        h.write_u32(std::process::id());
+        let dir = std::env::temp_dir().join(format!("aoc2022_d7a-{:016x}", h.finish()));
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e),
+        }
+    }
+    Err(std::io::Error::other(
+        "couldn't find an unused scratch directory name",
+    ))
+}
+
+fn verify_on_disk(fs: &FileSystem) -> Result<(), Box<dyn std::error::Error>> {
+    // only a directory this run created is ever removed
+    let root = scratch_dir()?;
+    let result = fs
+        .populate(&root)
+        .and_then(|_| Ok(FileSystem::from_disk(&root)?));
+    // a failed cleanup mustn't hide why populate or from_disk failed
+    if let Err(e) = std::fs::remove_dir_all(&root) {
+        eprintln!("couldn't remove {}: {}", root.display(), e);
+    }
+    let disk = result?;
+
+    let expected = fs.dir_totals();
+    let found = disk.dir_totals();
+    let mut mismatches = 0;
+    for (path, size) in &expected {
+        match found.get(path) {
+            Some(s) if s == size => {}
+            Some(s) => {
+                println!("{}: transcript {} but disk {}", path, size, s);
+                mismatches += 1;
+            }
+            None => {
+                println!("{}: missing on disk", path);
+                mismatches += 1;
             }
+        }
+    }
+    for path in found.keys().filter(|p| !expected.contains_key(*p)) {
+        println!("{}: only on disk", path);
+        mismatches += 1;
+    }
+
+    if mismatches > 0 {
+        return Err(format!("{} directories differ", mismatches).into());
+    }
+    println!("all {} directory totals match", expected.len());
+    Ok(())
+}
 
-            _ => file_sum += wordv[0].parse::<usize>()?,
+const DISK_CAPACITY: usize = 70_000_000;
+const UPDATE_SPACE: usize = 30_000_000;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let input = include_str!("../../directory_instrs.dat");
+    let fs = FileSystem::from_transcript(input)?;
+
+    // "cleanup [capacity] [required] [files]" ranks the sets of directories, or files
+    // and directories, whose deletion frees the required space
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|s| s.as_str()) == Some("cleanup") {
+        let capacity = match args.get(1) {
+            Some(c) => c.parse()?,
+            None => DISK_CAPACITY,
+        };
+        let required = match args.get(2) {
+            Some(r) => r.parse()?,
+            None => UPDATE_SPACE,
+        };
+        let files = args.get(3).map(|s| s.as_str()) == Some("files");
+        print_cleanup(&fs, capacity, required, files)?;
+        return Ok(());
+    }
+
+    // "materialize <dir>" builds the transcript's tree under dir with sparse files,
+    // "transcript <dir>" prints a transcript of a real directory and "verify" checks
+    // the transcript's sizes against a copy built in a scratch directory
+    match (args.first().map(|s| s.as_str()), args.get(1)) {
+        (Some("materialize"), Some(dir)) => {
+            fs.materialize(std::path::Path::new(dir))?;
+            return Ok(());
+        }
+        (Some("transcript"), Some(dir)) => {
+            print!(
+                "{}",
+                FileSystem::from_disk(std::path::Path::new(dir))?.to_transcript()
+            );
+            return Ok(());
+        }
+        (Some("verify"), _) => {
+            verify_on_disk(&fs)?;
+            return Ok(());
         }
+        _ => {}
     }
-    // save final dir size
-    store_directory_size(&mut dirsize, file_sum, &hm, &path)?;
+
+    // "du", "tree" or "json" argument prints the filesystem in that form
+    match args.first().map(|s| s.as_str()) {
+        Some("du") => {
+            print!("{}", fs.du());
+            return Ok(());
+        }
+        Some("tree") => {
+            print!("{}", fs.tree());
+            return Ok(());
+        }
+        Some("json") => {
+            println!("{}", fs.to_json());
+            return Ok(());
+        }
+        _ => {}
+    }
+
+    let sizes = fs.sizes();
+    let dirsize: Vec<usize> = fs.dirs().map(|d| sizes[d]).collect();
 
     let ans: usize = dirsize.iter().filter(|&s| *s < 100000).sum();
     println!("aoc7a: {}", ans);
 
-    let remaining = 70_000_000 - dirsize[0];
-    let needed = 30_000_000 - remaining;
+    let remaining = DISK_CAPACITY - dirsize[0];
+    let needed = UPDATE_SPACE - remaining;
 
     let mut smallest = usize::MAX;
     for size in dirsize {
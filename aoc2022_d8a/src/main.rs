@@ -1,75 +1,257 @@
 // AoC 2022 day 8
 
-// returns (number of visible trees, highest scenic score)
-fn score_visibility(v: &[Vec<u8>]) -> (usize, usize) {
-    let mut vis = 2 * v.len() + 2 * v[0].len() - 4;
-    let mut max_scenic = 0;
-
-    for x in 1..(v[0].len() - 1) {
-        for y in 1..(v.len() - 1) {
-            let height = v[y][x];
-            let mut sc = [x, v[0].len() - x - 1, y, v.len() - y - 1];
-
-            let mut blocked_left = false;
-            for xx in 1..=x {
-                if v[y][x - xx] >= height {
-                    blocked_left = true;
-                    sc[0] = xx;
-                    break;
-                }
-            }
+// visibility from outside the forest and viewing distances of every tree
+struct Survey {
+    visible: Vec<Vec<bool>>,
+    sc: Vec<Vec<[usize; 4]>>, // viewing distance left, right, up and down
+}
 
-            let mut blocked_right = false;
-            for xx in (x + 1)..v[0].len() {
-                if v[y][xx] >= height {
-                    blocked_right = true;
-                    sc[1] = xx - x;
-                    break;
-                }
-            }
+impl Survey {
+    fn scenic(&self, x: usize, y: usize) -> usize {
+        self.sc[y][x].iter().product()
+    }
 
-            let mut blocked_up = false;
-            for yy in 1..=y {
-                if v[y - yy][x] >= height {
-                    blocked_up = true;
-                    sc[2] = yy;
-                    break;
-                }
-            }
+    // the scenic score of every tree
+    fn scenic_map(&self) -> Vec<Vec<usize>> {
+        (0..self.sc.len())
+            .map(|y| (0..self.sc[y].len()).map(|x| self.scenic(x, y)).collect())
+            .collect()
+    }
+}
 
-            let mut blocked_down = false;
-            for yy in (y + 1)..v.len() {
-                if v[yy][x] >= height {
-                    blocked_down = true;
-                    sc[3] = yy - y;
-                    break;
-                }
+// look back along a line of trees, given as (x, y) in the order they are visited,
+// keeping a stack of the trees that could still block the view of those to come
+// each tree is pushed and popped at most once so the sweep is linear
+fn sweep(v: &[Vec<u32>], line: impl Iterator<Item = (usize, usize)>, dir: usize, s: &mut Survey) {
+    // (position along the line, height) with heights decreasing from bottom to top
+    let mut stack: Vec<(usize, u32)> = vec![];
+    for (pos, (x, y)) in line.enumerate() {
+        let height = v[y][x];
+        while stack.last().is_some_and(|&(_, h)| h < height) {
+            stack.pop();
+        }
+        match stack.last() {
+            Some(&(p, _)) => s.sc[y][x][dir] = pos - p,
+            None => {
+                s.sc[y][x][dir] = pos;
+                s.visible[y][x] = true;
             }
+        }
+        stack.push((pos, height));
+    }
+}
+
+// work out visibility and viewing distances with one sweep in each direction
+// along every row and column
+fn survey(v: &[Vec<u32>]) -> Survey {
+    let height = v.len();
+    let width = v.first().map_or(0, |r| r.len());
+    let mut s = Survey {
+        visible: vec![vec![false; width]; height],
+        sc: vec![vec![[0; 4]; width]; height],
+    };
 
-            // part 1 calculation
-            if !blocked_left || !blocked_right || !blocked_up || !blocked_down {
-                vis += 1;
+    for y in 0..height {
+        sweep(v, (0..width).map(|x| (x, y)), 0, &mut s);
+        sweep(v, (0..width).rev().map(|x| (x, y)), 1, &mut s);
+    }
+    for x in 0..width {
+        sweep(v, (0..height).map(|y| (x, y)), 2, &mut s);
+        sweep(v, (0..height).rev().map(|y| (x, y)), 3, &mut s);
+    }
+
+    s
+}
+
+// returns (number of visible trees, highest scenic score)
+fn score_visibility(v: &[Vec<u32>]) -> (usize, usize) {
+    let s = survey(v);
+    let vis = s.visible.iter().flatten().filter(|&&b| b).count();
+    let max_scenic = s.scenic_map().into_iter().flatten().max().unwrap_or(0);
+
+    (vis, max_scenic)
+}
+
+// parse a heightmap of single digit heights, or whitespace separated heights of any
+// size if multi_digit is set, rejecting ragged rows and bad heights
+fn parse_forest(input: &str, multi_digit: bool) -> Result<Vec<Vec<u32>>, String> {
+    let lines: Vec<&str> = input.trim_end().lines().collect();
+    let mut v: Vec<Vec<u32>> = vec![];
+
+    for (ln, l) in lines.iter().enumerate() {
+        let mut row = vec![];
+        if multi_digit {
+            for (col, w) in l.split_whitespace().enumerate() {
+                let h = w.parse::<u32>().map_err(|_| {
+                    format!(
+                        "line {}, column {}: '{}' is not a height",
+                        ln + 1,
+                        col + 1,
+                        w
+                    )
+                })?;
+                row.push(h);
             }
+        } else {
+            for (col, c) in l.chars().enumerate() {
+                let h = c.to_digit(10).ok_or(format!(
+                    "line {}, column {}: '{}' is not a digit",
+                    ln + 1,
+                    col + 1,
+                    c
+                ))?;
+                row.push(h);
+            }
+        }
 
-            // part 2 calculation
-            let scenic: usize = sc.iter().product();
-            if scenic > max_scenic {
-                max_scenic = scenic;
+        if row.is_empty() {
+            return Err(format!("line {} is empty", ln + 1));
+        }
+        if let Some(first) = v.first() {
+            if row.len() != first.len() {
+                return Err(format!(
+                    "line {} has {} trees but line 1 has {}",
+                    ln + 1,
+                    row.len(),
+                    first.len()
+                ));
             }
         }
+        v.push(row);
     }
 
-    (vis, max_scenic)
+    if v.is_empty() {
+        return Err("the forest has no trees".to_string());
+    }
+    Ok(v)
+}
+
+// draw the forest with trees visible from outside in bold green and the rest dimmed
+fn render_visibility(v: &[Vec<u32>], s: &Survey) -> String {
+    let width = v
+        .iter()
+        .flatten()
+        .map(|h| h.to_string().len())
+        .max()
+        .unwrap_or(1);
+    let sep = if width > 1 { " " } else { "" };
+    let mut out = String::new();
+    for (y, row) in v.iter().enumerate() {
+        let cells = row
+            .iter()
+            .enumerate()
+            .map(|(x, h)| {
+                let colour = if s.visible[y][x] { "1;32" } else { "2" };
+                format!("\x1b[{}m{:>w$}\x1b[0m", colour, h, w = width)
+            })
+            .collect::<Vec<String>>();
+        out += &cells.join(sep);
+        out.push('\n');
+    }
+    out
+}
+
+// the n best treehouse spots as (x, y), highest scenic score first
+fn top_spots(s: &Survey, n: usize) -> Vec<(usize, usize)> {
+    let scenic = s.scenic_map();
+    let mut spots: Vec<(usize, usize)> = (0..scenic.len())
+        .flat_map(|y| (0..scenic[y].len()).map(move |x| (x, y)))
+        .collect();
+    spots.sort_by_key(|&(x, y)| (std::cmp::Reverse(scenic[y][x]), y, x));
+    spots.truncate(n);
+    spots
+}
+
+// write the scenic scores as a binary PPM image, dark blue for the lowest score
+// through to yellow for the highest
+fn write_heatmap(path: &str, s: &Survey) -> std::io::Result<()> {
+    let scenic = s.scenic_map();
+    let height = scenic.len();
+    let width = scenic.first().map_or(0, |r| r.len());
+    let max = scenic.iter().flatten().copied().max().unwrap_or(0).max(1) as f64;
+
+    let mut img = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    for row in &scenic {
+        for &sc in row {
+            let t = sc as f64 / max;
+            img.push((255.0 * t.sqrt()) as u8);
+            img.push((255.0 * t) as u8);
+            img.push((128.0 * (1.0 - t)) as u8);
+        }
+    }
+    std::fs::write(path, img)
+}
+
+// print the visibility map and the n best spots with their viewing distances,
+// optionally writing a scenic score heatmap image
+fn print_report(v: &[Vec<u32>], n: usize, heatmap: Option<&str>) -> std::io::Result<()> {
+    let s = survey(v);
+    print!("{}", render_visibility(v, &s));
+    println!(
+        "{} of {} trees visible",
+        s.visible.iter().flatten().filter(|&&b| b).count(),
+        v.len() * v[0].len()
+    );
+
+    println!("top {} scenic spots:", n);
+    for (rank, (x, y)) in top_spots(&s, n).into_iter().enumerate() {
+        let sc = s.sc[y][x];
+        println!(
+            "{:>3}. ({}, {}) height {}, score {}: left {}, right {}, up {}, down {}",
+            rank + 1,
+            x,
+            y,
+            v[y][x],
+            s.scenic(x, y),
+            sc[0],
+            sc[1],
+            sc[2],
+            sc[3]
+        );
+    }
+
+    if let Some(path) = heatmap {
+        write_heatmap(path, &s)?;
+        println!("heatmap written to {}", path);
+    }
+    Ok(())
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let input = include_str!("../../trees.dat");
 
-    let v: Vec<Vec<u8>> = input
-        .split('\n')
-        .into_iter()
-        .map(|s| s.chars().map(|c| c as u8 - b'0').collect::<Vec<u8>>())
-        .collect::<Vec<Vec<u8>>>();
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    // "--multi <file>" reads whitespace separated heights of any size from the file
+    // instead of the single digit puzzle input
+    let v = match args.iter().position(|a| a == "--multi") {
+        Some(m) => {
+            let path = args.get(m + 1).ok_or("--multi needs a file name")?;
+            parse_forest(&std::fs::read_to_string(path)?, true)?
+        }
+        None => parse_forest(input, false)?,
+    };
+
+    // "scenic" argument prints the scenic score of every tree
+    if args.iter().any(|a| a == "scenic") {
+        for row in survey(&v).scenic_map() {
+            let r = row.iter().map(|s| s.to_string()).collect::<Vec<String>>();
+            println!("{}", r.join(" "));
+        }
+        return Ok(());
+    }
+
+    // "report [n] [--heatmap file.ppm]" prints the visibility map and n best spots
+    if let Some(pos) = args.iter().position(|a| a == "report") {
+        let n = args.get(pos + 1).and_then(|a| a.parse().ok()).unwrap_or(10);
+        let heatmap = args
+            .iter()
+            .position(|a| a == "--heatmap")
+            .map(|h| args.get(h + 1).ok_or("--heatmap needs a file name"))
+            .transpose()?;
+        print_report(&v, n, heatmap.map(|h| h.as_str()))?;
+        return Ok(());
+    }
 
     let (ansa, ansb) = score_visibility(&v);
     println!("aoc8a: {}, aoc8b: {}", ansa, ansb);
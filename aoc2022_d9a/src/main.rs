@@ -1,6 +1,6 @@
 // AoC 2022 day 9
 
-use std::collections::HashSet;
+use std::collections::HashMap;
 
 // custom error type
 #[derive(Debug)]
@@ -42,100 +42,464 @@ impl Point {
 }
 
 // function that implements knot "motion"
+// a knot that no longer touches the one it follows takes one step, straight or
+// diagonal, towards it, however far away that knot is
 fn knot_step(from: Point, to: Point) -> Point {
-    let mut m = Point::new(0, 0);
-    if to.x == 2 + from.x {
-        m.x = 1;
-        if to.y - from.y >= 1 {
-            m.y = 1;
-        } else if from.y - to.y >= 1 {
-            m.y = -1;
+    let dx = to.x - from.x;
+    let dy = to.y - from.y;
+    if dx.abs() <= 1 && dy.abs() <= 1 {
+        Point::new(0, 0)
+    } else {
+        Point::new(dx.signum(), dy.signum())
+    }
+}
+
+// a rope of knots where knot 0 is the head, each knot follows the one before it
+// the cells visited by every knot are recorded, not just the tail's, along with
+// how many times each knot moved into them
+struct Rope {
+    knots: Vec<Point>,
+    visited: Vec<HashMap<Point, usize>>,
+    frames: Option<Vec<Vec<Point>>>, // knot positions after every step, if recording
+}
+
+impl Rope {
+    // a rope of n knots all starting at the origin
+    fn new(n: usize) -> Self {
+        let start = Point::new(0, 0);
+        Self {
+            knots: vec![start; n],
+            visited: vec![HashMap::from([(start, 1)]); n],
+            frames: None,
         }
-    } else if to.x + 2 == from.x {
-        m.x = -1;
-        if to.y - from.y >= 1 {
-            m.y = 1;
-        } else if from.y - to.y >= 1 {
-            m.y = -1;
+    }
+
+    // a rope that also keeps the position of every knot after each step
+    fn recording(n: usize) -> Self {
+        let mut rope = Rope::new(n);
+        rope.frames = Some(vec![rope.knots.clone()]);
+        rope
+    }
+
+    fn visit(&mut self, idx: usize) {
+        *self.visited[idx].entry(self.knots[idx]).or_insert(0) += 1;
+    }
+
+    fn snapshot(&mut self) {
+        if let Some(frames) = self.frames.as_mut() {
+            frames.push(self.knots.clone());
         }
     }
 
-    if to.y == 2 + from.y {
-        m.y = 1;
-        if to.x - from.x >= 1 {
-            m.x = 1;
-        } else if from.x - to.x >= 1 {
-            m.x = -1;
+    // move every knot after the head one step towards the knot before it,
+    // returns false if none of them needed to move
+    fn follow(&mut self) -> bool {
+        let mut moved = false;
+        for idx in 1..self.knots.len() {
+            let move_seg = knot_step(self.knots[idx], self.knots[idx - 1]);
+            if move_seg != Point::new(0, 0) {
+                self.knots[idx].x += move_seg.x;
+                self.knots[idx].y += move_seg.y;
+                self.visit(idx);
+                moved = true;
+            }
         }
-    } else if to.y + 2 == from.y {
-        m.y = -1;
-        if to.x - from.x >= 1 {
-            m.x = 1;
-        } else if from.x - to.x >= 1 {
-            m.x = -1;
+        moved
+    }
+
+    // move the head one step and let the rest of the knots follow
+    fn step_head(&mut self, dir: Point) {
+        self.knots[0].x += dir.x;
+        self.knots[0].y += dir.y;
+        self.visit(0);
+        self.follow();
+        self.snapshot();
+    }
+
+    // put the head straight at a cell, the rest of the knots then step after it
+    // until the rope is taut again
+    fn jump_head(&mut self, to: Point) {
+        self.knots[0] = to;
+        self.visit(0);
+        self.snapshot();
+        while self.follow() {
+            self.snapshot();
+        }
+    }
+
+    fn move_head(&mut self, dir: Point, dist: i32) {
+        for _ in 0..dist {
+            self.step_head(dir);
         }
     }
-    m
+
+    // the number of different cells each knot has visited, head first
+    fn visited_counts(&self) -> Vec<usize> {
+        self.visited.iter().map(|v| v.len()).collect()
+    }
+
+    fn tail_visited(&self) -> usize {
+        self.visited.last().map_or(0, |v| v.len())
+    }
 }
 
-// move an array of connected knots
-fn move_cord(dir: Point, dist: i32, cord: &mut [Point], v: &mut HashSet<Point>) {
-    for _ in 0..dist {
-        cord[0].x += dir.x;
-        cord[0].y += dir.y;
-        for idx in 1..cord.len() {
-            let move_seg = knot_step(cord[idx], cord[idx - 1]);
-            cord[idx].x += move_seg.x;
-            cord[idx].y += move_seg.y;
+// the grid of cells that fits every cell any knot visited, y increasing upwards
+struct Canvas {
+    min: Point,
+    width: usize,
+    height: usize,
+}
+
+// the most cells a canvas may have, so no grid or image drawn on it gets out of hand
+const MAX_CANVAS_CELLS: usize = 1 << 22;
+
+impl Canvas {
+    fn covering(rope: &Rope) -> Result<Self, Error> {
+        let cells: Vec<Point> = rope
+            .visited
+            .iter()
+            .flat_map(|v| v.keys())
+            .copied()
+            .collect();
+        let min = Point::new(
+            cells.iter().map(|p| p.x).min().unwrap_or(0),
+            cells.iter().map(|p| p.y).min().unwrap_or(0),
+        );
+        let max = Point::new(
+            cells.iter().map(|p| p.x).max().unwrap_or(0),
+            cells.iter().map(|p| p.y).max().unwrap_or(0),
+        );
+        let width = (max.x as i64 - min.x as i64 + 1) as usize;
+        let height = (max.y as i64 - min.y as i64 + 1) as usize;
+        if width
+            .checked_mul(height)
+            .is_none_or(|c| c > MAX_CANVAS_CELLS)
+        {
+            return Err(Error::new(&format!(
+                "Error: a {} x {} canvas is too large to draw, the limit is {} cells",
+                width, height, MAX_CANVAS_CELLS
+            )));
         }
-        v.insert(cord[cord.len() - 1]);
+        Ok(Self { min, width, height })
+    }
+
+    // (column, row) of a cell, row 0 at the top
+    fn index(&self, p: Point) -> (usize, usize) {
+        (
+            (p.x - self.min.x) as usize,
+            self.height - 1 - (p.y - self.min.y) as usize,
+        )
+    }
+
+    // a blank grid of the canvas size
+    fn grid<T: Clone>(&self, fill: T) -> Vec<Vec<T>> {
+        vec![vec![fill; self.width]; self.height]
+    }
+}
+
+// the character for knot idx: H for the head, then 1-9 and a-z, * beyond that
+fn knot_char(idx: usize) -> char {
+    match idx {
+        0 => 'H',
+        1..=35 => std::char::from_digit(idx as u32, 36).unwrap(),
+        _ => '*',
+    }
+}
+
+// draw one frame as text: the knots over the cells the tail has visited so far,
+// with s marking the start
+fn render_frame(cv: &Canvas, knots: &[Point], trail: &HashMap<Point, usize>) -> String {
+    let mut g = cv.grid('.');
+    for p in trail.keys() {
+        let (c, r) = cv.index(*p);
+        g[r][c] = '#';
+    }
+    let (c, r) = cv.index(Point::new(0, 0));
+    g[r][c] = 's';
+    // draw back to front so leading knots cover the ones behind them
+    for (idx, p) in knots.iter().enumerate().rev() {
+        let (c, r) = cv.index(*p);
+        g[r][c] = knot_char(idx);
+    }
+    g.iter()
+        .map(|row| row.iter().collect::<String>() + "\n")
+        .collect()
+}
+
+// how often the tail moved into each cell, scaled to 0..=1
+fn heat(cv: &Canvas, rope: &Rope) -> Vec<Vec<f64>> {
+    let tail = rope.visited.last().unwrap();
+    let max = tail.values().copied().max().unwrap_or(1) as f64;
+    let mut g = cv.grid(0.0);
+    for (p, n) in tail {
+        let (c, r) = cv.index(*p);
+        g[r][c] = *n as f64 / max;
+    }
+    g
+}
+
+// the tail's visit frequency as text, from ' ' for never through to '@' for most
+fn render_heatmap(cv: &Canvas, rope: &Rope) -> String {
+    const RAMP: &[u8] = b" .:-=+*#%@";
+    heat(cv, rope)
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&h| {
+                    if h == 0.0 {
+                        ' '
+                    } else {
+                        RAMP[1 + (h * (RAMP.len() - 2) as f64).round() as usize] as char
+                    }
+                })
+                .collect::<String>()
+                + "\n"
+        })
+        .collect()
+}
+
+// write an RGB grid as a binary PPM image, each cell drawn as a scale x scale block
+fn write_ppm(path: &std::path::Path, g: &[Vec<[u8; 3]>], scale: usize) -> std::io::Result<()> {
+    let height = g.len() * scale;
+    let width = g.first().map_or(0, |r| r.len()) * scale;
+    let mut img = format!("P6\n{} {}\n255\n", width, height).into_bytes();
+    for row in g {
+        for _ in 0..scale {
+            for px in row {
+                for _ in 0..scale {
+                    img.extend_from_slice(px);
+                }
+            }
+        }
+    }
+    std::fs::write(path, img)
+}
+
+// largest total size of frame images write_frame_images will agree to write
+const MAX_FRAME_BYTES: usize = 1 << 30;
+
+// true if frame f of count is drawn when keeping every stride'th frame, the last
+// frame always is
+fn keep_frame(f: usize, count: usize, every: usize) -> bool {
+    f.is_multiple_of(every) || f + 1 == count
+}
+
+// write every stride'th recorded frame as dir/frame_NNNNN.ppm, the tail's trail in grey,
+// the head in red shading through to the tail in blue
+// nothing is written if the images would add up to more than MAX_FRAME_BYTES
+fn write_frame_images(dir: &str, cv: &Canvas, rope: &Rope, every: usize) -> std::io::Result<()> {
+    let frames = rope.frames.as_deref().unwrap_or_default();
+    let selected = (0..frames.len())
+        .filter(|&f| keep_frame(f, frames.len(), every))
+        .count();
+    let frame_bytes = cv
+        .width
+        .checked_mul(cv.height)
+        .and_then(|c| c.checked_mul(4 * 4 * 3))
+        .unwrap_or(usize::MAX);
+    if frame_bytes > MAX_FRAME_BYTES {
+        return Err(std::io::Error::other(format!(
+            "the {} x {} canvas is too large, one frame would need {} MiB",
+            cv.width,
+            cv.height,
+            frame_bytes >> 20
+        )));
+    }
+    let total = frame_bytes.saturating_mul(selected);
+    if total > MAX_FRAME_BYTES {
+        return Err(std::io::Error::other(format!(
+            "{} frames of {} bytes would need {} MiB, use a larger --every stride",
+            selected,
+            frame_bytes,
+            total >> 20
+        )));
+    }
+
+    std::fs::create_dir_all(dir)?;
+    let n = rope.knots.len().max(2) - 1;
+    let mut trail = cv.grid(false);
+    for (f, knots) in frames.iter().enumerate() {
+        let (c, r) = cv.index(*knots.last().unwrap());
+        trail[r][c] = true;
+        if !keep_frame(f, frames.len(), every) {
+            continue;
+        }
+        let mut g: Vec<Vec<[u8; 3]>> = trail
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|&t| if t { [96, 96, 96] } else { [0, 0, 0] })
+                    .collect()
+            })
+            .collect();
+        for (idx, p) in knots.iter().enumerate().rev() {
+            let (c, r) = cv.index(*p);
+            let t = idx as f64 / n as f64;
+            g[r][c] = [(255.0 * (1.0 - t)) as u8, 64, (255.0 * t) as u8];
+        }
+        let path = std::path::Path::new(dir).join(format!("frame_{:05}.ppm", f));
+        write_ppm(&path, &g, 4)?;
+    }
+    Ok(())
+}
+
+// write the tail's visit frequency as dir/heatmap.ppm, black through red to yellow
+fn write_heatmap_image(dir: &str, cv: &Canvas, rope: &Rope) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    let g: Vec<Vec<[u8; 3]>> = heat(cv, rope)
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|&h| {
+                    [
+                        (255.0 * (2.0 * h).min(1.0)) as u8,
+                        (255.0 * (2.0 * h - 1.0).max(0.0)) as u8,
+                        0,
+                    ]
+                })
+                .collect()
+        })
+        .collect();
+    write_ppm(&std::path::Path::new(dir).join("heatmap.ppm"), &g, 4)
+}
+
+// a head movement: steps in one of the eight directions or a jump to a cell
+#[derive(Debug, Clone, Copy)]
+enum Command {
+    Step(Point, i32),
+    Goto(Point),
+}
+
+// the unit step for a direction such as "R", "UL" or "DR"
+fn parse_direction(d: &str) -> Result<Point, Error> {
+    let mut dir = Point::new(0, 0);
+    for c in d.chars() {
+        match c {
+            'R' if dir.x == 0 => dir.x = 1,
+            'L' if dir.x == 0 => dir.x = -1,
+            'U' if dir.y == 0 => dir.y = 1,
+            'D' if dir.y == 0 => dir.y = -1,
+            _ => return Err(Error::new(&format!("Error: bad direction {}", d))),
+        }
+    }
+    if dir == Point::new(0, 0) {
+        return Err(Error::new("Error: missing direction"));
+    }
+    Ok(dir)
+}
+
+// parse an "R 4", "UL 2" or "goto 3 -1" command
+fn parse_command(com: &str) -> Result<Command, Box<dyn std::error::Error>> {
+    let c: Vec<&str> = com.split_whitespace().collect();
+    match c.as_slice() {
+        ["goto", x, y] => Ok(Command::Goto(Point::new(x.parse()?, y.parse()?))),
+        [d, dist] => Ok(Command::Step(parse_direction(d)?, dist.parse()?)),
+        _ => Err(Box::new(Error::new(&format!("Error: bad command {}", com)))),
     }
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let input = include_str!("../../head_moves.dat");
-    let mut visited: HashSet<Point> = HashSet::new();
-    let mut cord_tail_visited: HashSet<Point> = HashSet::new();
-    let mut hloc = Point::new(0, 0);
-    let mut tloc = Point::new(0, 0);
-    let mut cord: Vec<Point> = vec![Point::new(0, 0); 10];
 
-    let coms: Vec<&str> = input.split('\n').collect();
+    // "knots <n>" simulates a rope of n knots and prints the cells each knot visited
+    // "frames [n]" and "heatmap [n]" draw a rope of n knots, default 10, as text,
+    // or as images written to a directory if "--ppm <dir>" follows, "--every <k>"
+    // keeps only every k'th frame
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let mode = args.first().map(|s| s.as_str());
+    let mut ropes = match (mode, args.get(1)) {
+        (Some("knots"), Some(n)) => vec![Rope::new(n.parse()?)],
+        (Some("knots"), None) => {
+            return Err(Box::new(Error::new("Error: knots needs a knot count")))
+        }
+        (Some("frames") | Some("heatmap"), n) => {
+            let n = match n {
+                Some(n) if !n.starts_with("--") => n.parse()?,
+                _ => 10,
+            };
+            vec![Rope::recording(n)]
+        }
+        _ => vec![Rope::new(2), Rope::new(10)],
+    };
+    if ropes.iter().any(|r| r.knots.is_empty()) {
+        return Err(Box::new(Error::new(
+            "Error: a rope needs at least one knot",
+        )));
+    }
 
-    for com in coms {
+    for com in input.lines() {
         if com.is_empty() {
             continue;
         }
 
-        let c: Vec<&str> = com.split(' ').collect();
-        let dir: Point = match c[0] {
-            "R" => Point::new(1, 0),
-            "L" => Point::new(-1, 0),
-            "U" => Point::new(0, 1),
-            "D" => Point::new(0, -1),
-            _ => return Err(Box::new(Error::new("Error: bad direction"))),
-        };
-        let dist: i32 = c[1].parse()?;
-
-        // part 1 - move two knots
-        for _ in 0..dist {
-            hloc.x += dir.x;
-            hloc.y += dir.y;
-            let move_seg = knot_step(tloc, hloc);
-            tloc.x += move_seg.x;
-            tloc.y += move_seg.y;
-
-            visited.insert(tloc);
+        let command = parse_command(com)?;
+        for rope in ropes.iter_mut() {
+            match command {
+                Command::Step(dir, dist) => rope.move_head(dir, dist),
+                Command::Goto(to) => rope.jump_head(to),
+            }
         }
+    }
 
-        // part 2 - move a cord of knots
-        move_cord(dir, dist, &mut cord, &mut cord_tail_visited);
+    let ppm = match args.iter().position(|a| a == "--ppm") {
+        Some(i) => Some(
+            args.get(i + 1)
+                .ok_or(Error::new("Error: --ppm needs a directory"))?,
+        ),
+        None => None,
+    };
+    let every = match args.iter().position(|a| a == "--every") {
+        Some(i) => match args.get(i + 1).and_then(|k| k.parse::<usize>().ok()) {
+            Some(k) if k > 0 => k,
+            _ => {
+                return Err(Box::new(Error::new(
+                    "Error: --every needs a stride of at least 1",
+                )))
+            }
+        },
+        None => 1,
+    };
+    match mode {
+        Some("knots") => {
+            for (i, n) in ropes[0].visited_counts().iter().enumerate() {
+                println!("knot {}: {}", i, n);
+            }
+            return Ok(());
+        }
+        Some("frames") => {
+            let rope = &ropes[0];
+            let cv = Canvas::covering(rope)?;
+            match ppm {
+                Some(dir) => write_frame_images(dir, &cv, rope, every)?,
+                None => {
+                    let tail = rope.knots.len() - 1;
+                    let mut trail = HashMap::new();
+                    let frames = rope.frames.as_deref().unwrap_or_default();
+                    for (f, knots) in frames.iter().enumerate() {
+                        trail.insert(knots[tail], 1);
+                        if keep_frame(f, frames.len(), every) {
+                            println!("{}", render_frame(&cv, knots, &trail));
+                        }
+                    }
+                }
+            }
+            return Ok(());
+        }
+        Some("heatmap") => {
+            let cv = Canvas::covering(&ropes[0])?;
+            match ppm {
+                Some(dir) => write_heatmap_image(dir, &cv, &ropes[0])?,
+                None => print!("{}", render_heatmap(&cv, &ropes[0])),
+            }
+            return Ok(());
+        }
+        _ => {}
     }
 
     println!(
         "aoc9a: {}, aoc9b: {}",
-        visited.len(),
-        cord_tail_visited.len()
+        ropes[0].tail_visited(),
+        ropes[1].tail_visited()
     );
 
     Ok(())